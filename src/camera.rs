@@ -6,15 +6,48 @@ use std::f32::consts::PI;
 use bevy::input::gamepad::GamepadEvent;
 use bevy::input::mouse::{MouseMotion, MouseWheel};
 use bevy::prelude::*;
+#[cfg(feature = "ui")]
 use bevy_inspector_egui::bevy_egui::EguiContexts;
+use bevy_rapier3d::prelude::RigidBody;
 
 pub struct OrbitCameraPlugin;
 
 impl Plugin for OrbitCameraPlugin {
     fn build(&self, app: &mut App) {
         app.register_type::<OrbitCamera>();
+        app.register_type::<OrbitPivot>();
+        app.register_type::<OrbitCameraSettings>();
+        app.init_resource::<OrbitCameraSettings>();
         app.add_systems(Update, apply_camera_controls);
-        app.add_systems(Update, update_camera.after(apply_camera_controls));
+        app.add_systems(Update, frame_all.after(apply_camera_controls).run_if(crate::keyboard_free));
+        app.add_systems(Update, update_camera.after(frame_all));
+    }
+}
+
+#[derive(Debug, Resource, Reflect)]
+#[reflect(Resource)]
+pub struct OrbitCameraSettings {
+    // moves camera so that all dynamic bodies are visible
+    pub frame_all_key: Option<KeyCode>,
+    // multiplier for distance needed to fit bodies into view
+    pub frame_padding: f32,
+    pub frame_speed: f32,
+    // mouse movement in pixels after pressing a button before drag starts,
+    // so clicks with a slightly moving mouse don't move the camera
+    pub drag_deadzone: f32,
+    // starting view, see `OrbitCamera::from_transform`
+    pub initial_transform: Transform,
+}
+
+impl Default for OrbitCameraSettings {
+    fn default() -> Self {
+        Self {
+            frame_all_key: Some(KeyCode::F),
+            frame_padding: 1.2,
+            frame_speed: 8.,
+            drag_deadzone: 3.,
+            initial_transform: Transform::from_xyz(-5., 7.5, 16.).looking_at(Vec3::Y, Vec3::Y),
+        }
     }
 }
 
@@ -36,9 +69,56 @@ pub struct OrbitCamera {
     pub max_distance: f32,
     pub min_y_angle: f32,
     pub max_y_angle: f32,
-    pub target: Vec3,
+    // focus point relative to the pivot, moved by panning
+    pub offset: Vec3,
+    pub pivot: OrbitPivot,
+    // how fast the pivot follows its position (per second), 0 means instantly
+    pub pivot_smoothing: f32,
+    // smoothed pivot position, see `current_pivot`
+    current_pivot: Vec3,
     pub active: bool,
     pub last_rotation: Quat,
+    // focus point and distance camera is moving towards after "frame all"
+    pub framing: Option<(Vec3, f32)>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Reflect)]
+pub enum OrbitPivot {
+    Fixed(Vec3),
+    Origin,
+    // mean position of all dynamic bodies, recomputed every frame
+    Centroid,
+    // position of given entity, last known one is kept after it's despawned
+    Entity(Entity),
+}
+
+impl OrbitCamera {
+    // camera orbits the pivot, so only rotation and distance from the origin are taken
+    pub fn from_transform(transform: Transform) -> Self {
+        let (yaw, pitch, _roll) = transform.rotation.to_euler(EulerRot::YXZ);
+        Self {
+            gimbal_x: -yaw,
+            gimbal_y: -pitch,
+            distance: transform.translation.length(),
+            ..default()
+        }
+    }
+
+    // point the camera is currently looking at
+    pub fn focus(&self) -> Vec3 {
+        self.current_pivot + self.offset
+    }
+
+    // pivot position the camera currently orbits, lags behind `pivot` with smoothing
+    pub fn current_pivot(&self) -> Vec3 {
+        self.current_pivot
+    }
+}
+
+impl Default for OrbitPivot {
+    fn default() -> Self {
+        Self::Origin
+    }
 }
 
 impl Default for OrbitCamera {
@@ -54,9 +134,13 @@ impl Default for OrbitCamera {
             max_distance: f32::INFINITY,
             min_y_angle: 0.02,
             max_y_angle: PI / 2.2,
-            target: Vec3::ZERO,
+            offset: Vec3::ZERO,
+            pivot: OrbitPivot::default(),
+            pivot_smoothing: 5.,
+            current_pivot: Vec3::ZERO,
             active: true,
             last_rotation: Quat::IDENTITY,
+            framing: None,
         }
     }
 }
@@ -83,11 +167,18 @@ fn apply_camera_controls(
     mut gamepad_state: Local<GamepadState>,
     time: Res<Time>,
     buttons: Res<Input<MouseButton>>,
+    #[cfg(feature = "ui")]
     mut egui_contexts: EguiContexts,
     mut camera_query: Query<&mut OrbitCamera>,
+    settings: Res<OrbitCameraSettings>,
+    mut drag_distance: Local<f32>,
 ) {
-    let egui_ctx = egui_contexts.ctx_mut();
-    if egui_ctx.wants_pointer_input() { return; }
+    if !buttons.any_pressed([MouseButton::Left, MouseButton::Right]) {
+        *drag_distance = 0.;
+    }
+
+    #[cfg(feature = "ui")]
+    if egui_contexts.ctx_mut().wants_pointer_input() { return; }
 
     enum MyEvent {
         Zoom(f32),
@@ -103,10 +194,18 @@ fn apply_camera_controls(
 
     if buttons.pressed(MouseButton::Left) {
         for ev in move_events.read() {
+            if *drag_distance < settings.drag_deadzone {
+                *drag_distance += ev.delta.length();
+                continue;
+            }
             events.push(MyEvent::Rotate((ev.delta.x, ev.delta.y)));
         }
     } else if buttons.pressed(MouseButton::Right) {
         for ev in move_events.read() {
+            if *drag_distance < settings.drag_deadzone {
+                *drag_distance += ev.delta.length();
+                continue;
+            }
             events.push(MyEvent::Pan((ev.delta.x, ev.delta.y)));
         }
     }
@@ -172,6 +271,8 @@ fn apply_camera_controls(
     for mut camera in camera_query.iter_mut() {
         if !camera.active { return; }
         camcount += 1;
+        // manual control cancels "frame all" animation
+        camera.framing = None;
 
         for event in events.iter() {
             match event {
@@ -186,8 +287,8 @@ fn apply_camera_controls(
                 }
                 MyEvent::Pan((dx, dy)) => {
                     let v = Vec2::new(*dx, *dy).rotate(-Vec2::from_angle(camera.gimbal_x));
-                    camera.target.x += v.x * camera.pan_sensitivity * camera.distance;
-                    camera.target.z += v.y * camera.pan_sensitivity * camera.distance;
+                    camera.offset.x += v.x * camera.pan_sensitivity * camera.distance;
+                    camera.offset.z += v.y * camera.pan_sensitivity * camera.distance;
                 }
             }
         }
@@ -198,27 +299,99 @@ fn apply_camera_controls(
     }
 }
 
+fn frame_all(
+    settings: Res<OrbitCameraSettings>,
+    keys: Res<Input<KeyCode>>,
+    mut camera_query: Query<(&mut OrbitCamera, Option<&Projection>)>,
+    bodies: Query<(&GlobalTransform, &RigidBody)>,
+) {
+    let Some(key) = settings.frame_all_key else { return; };
+    if !keys.just_pressed(key) { return; }
+
+    let positions = bodies.iter()
+        .filter(|(_, body)| matches!(body, RigidBody::Dynamic))
+        .map(|(transform, _)| transform.translation())
+        .collect::<Vec<_>>();
+    if positions.is_empty() { return; }
+
+    let min = positions.iter().copied().fold(Vec3::INFINITY, Vec3::min);
+    let max = positions.iter().copied().fold(Vec3::NEG_INFINITY, Vec3::max);
+    let center = (min + max) / 2.;
+    // bodies are not points, so leave some room around their centers
+    let radius = positions.iter().map(|p| p.distance(center)).fold(0., f32::max) + 1.;
+
+    for (mut camera, projection) in camera_query.iter_mut() {
+        let fov = match projection {
+            Some(Projection::Perspective(perspective)) => perspective.fov,
+            _ => std::f32::consts::FRAC_PI_4,
+        };
+        let distance = (radius / (fov / 2.).sin() * settings.frame_padding)
+            .clamp(camera.min_distance, camera.max_distance);
+        camera.framing = Some((center, distance));
+    }
+}
+
 fn update_camera(
     mut commands: Commands,
     mut camera_query: Query<(Entity, &mut OrbitCamera)>,
+    bodies: Query<(&GlobalTransform, &RigidBody)>,
+    transforms: Query<&GlobalTransform>,
+    settings: Res<OrbitCameraSettings>,
     time: Res<Time>,
 ) {
     let delta = time.delta_seconds();
     let focus_rotation = Quat::IDENTITY;
 
+    let mut body_sum = Vec3::ZERO;
+    let mut body_count = 0;
+    for (transform, body) in bodies.iter() {
+        if !matches!(body, RigidBody::Dynamic) { continue; }
+        body_sum += transform.translation();
+        body_count += 1;
+    }
+    let centroid = (body_count > 0).then(|| body_sum / body_count as f32);
+
     for (entity, mut camera) in camera_query.iter_mut() {
         if !camera.active { return; }
 
+        let pivot = match camera.pivot {
+            OrbitPivot::Fixed(point) => point,
+            OrbitPivot::Origin => Vec3::ZERO,
+            OrbitPivot::Centroid => centroid.unwrap_or(camera.current_pivot),
+            OrbitPivot::Entity(target) => transforms.get(target)
+                .map(|transform| transform.translation())
+                .unwrap_or(camera.current_pivot),
+        };
+
+        camera.current_pivot = if camera.pivot_smoothing > 0. {
+            camera.current_pivot.lerp(pivot, (delta * camera.pivot_smoothing).min(1.))
+        } else {
+            pivot
+        };
+
+        if let Some((framing_focus, framing_distance)) = camera.framing {
+            let t = (delta * settings.frame_speed).min(1.);
+            let offset = framing_focus - camera.current_pivot;
+            camera.offset = camera.offset.lerp(offset, t);
+            camera.distance += (framing_distance - camera.distance) * t;
+
+            if camera.offset.distance(offset) < 0.01 && (camera.distance - framing_distance).abs() < 0.01 {
+                camera.framing = None;
+            }
+        }
+
+        let focus = camera.focus();
+
         camera.last_rotation = focus_rotation.slerp(camera.last_rotation, 1. - delta * 10.);
 
         let quat = Quat::from_euler(EulerRot::YXZ, -camera.gimbal_x, -camera.gimbal_y, 0.);
 
         let mut new_transform = Transform::from_translation(
-            camera.target +
+            focus +
             (camera.last_rotation * quat * Vec3::Z) * camera.distance
         );
 
-        new_transform.look_at(camera.target, camera.last_rotation * Vec3::Y);
+        new_transform.look_at(focus, camera.last_rotation * Vec3::Y);
 
         commands.entity(entity).insert(new_transform);
     }
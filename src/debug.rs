@@ -0,0 +1,499 @@
+// tools for inspecting rapier internals at a specific tick
+
+use std::time::Duration;
+
+use bevy::ecs::schedule::ScheduleLabel;
+use bevy::utils::HashMap;
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+
+use crate::time::{run_physics_schedule, BodySnapshot, LoopRegion, PhysicsSchedule, PhysicsTime, PhysicsTimeExt, PhysicsTimeMode};
+
+pub struct DebugPlugin;
+
+impl Plugin for DebugPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .register_type::<ContactDebugSettings>()
+            .init_resource::<ContactDebugSettings>()
+            .register_type::<ShowVelocityGizmos>()
+            .init_resource::<ShowVelocityGizmos>()
+            .register_type::<ConstraintRelaxation>()
+            .init_resource::<ConstraintRelaxation>()
+            .register_type::<TunnelingDetector>()
+            .init_resource::<TunnelingDetector>()
+            .add_event::<TunnelingWarning>()
+            .register_type::<PeekSettings>()
+            .init_resource::<PeekSettings>()
+            .init_resource::<PeekState>()
+            .add_systems(PreUpdate, peek.before(run_physics_schedule))
+            .register_type::<ShowGroundContacts>()
+            .init_resource::<ShowGroundContacts>()
+            .register_type::<ReplayFidelityCheck>()
+            .init_resource::<ReplayFidelityCheck>()
+            .add_systems(PhysicsSchedule, (
+                detect_tunneling.after(PhysicsSet::Writeback),
+                check_replay_fidelity.after(PhysicsSet::Writeback),
+                collect_ground_contacts.after(PhysicsSet::StepSimulation),
+            ))
+            .init_schedule(RelaxSchedule)
+            .add_systems(RelaxSchedule, (
+                RapierPhysicsPlugin::<NoUserData>::get_systems(PhysicsSet::SyncBackend)
+                    .in_set(PhysicsSet::SyncBackend),
+                RapierPhysicsPlugin::<NoUserData>::get_systems(PhysicsSet::StepSimulation)
+                    .in_set(PhysicsSet::StepSimulation),
+                RapierPhysicsPlugin::<NoUserData>::get_systems(PhysicsSet::Writeback)
+                    .in_set(PhysicsSet::Writeback),
+            ))
+            .add_systems(PreUpdate, relax_constraints.after(run_physics_schedule).run_if(crate::keyboard_free))
+            .add_systems(Update, (
+                dump_contacts.run_if(crate::keyboard_free),
+                draw_contacts, draw_velocities, draw_replay_reference, draw_ground_contacts, log_tunneling,
+            ));
+    }
+}
+
+#[derive(Reflect, Resource)]
+#[reflect(Resource)]
+pub struct ContactDebugSettings {
+    // logs all active contact manifolds, only while paused
+    pub dump_key: Option<KeyCode>,
+    pub draw: bool,
+}
+
+impl Default for ContactDebugSettings {
+    fn default() -> Self {
+        Self {
+            dump_key: Some(KeyCode::F10),
+            draw: false,
+        }
+    }
+}
+
+// velocity of each body as arrow, green when slow and red at `max_speed` and above
+#[derive(Reflect, Resource)]
+#[reflect(Resource)]
+pub struct ShowVelocityGizmos {
+    pub enabled: bool,
+    // arrow length per 1 m/s
+    pub scale: f32,
+    pub max_speed: f32,
+}
+
+impl Default for ShowVelocityGizmos {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            scale: 0.2,
+            max_speed: 10.,
+        }
+    }
+}
+
+// Runs rapier steps while paused, without advancing physics time or running `PhysicsSchedule`.
+//
+// Rapier accepts zero dt (its inverse dt becomes zero), but then nothing is integrated
+// and position correction is scaled by inverse dt as well, so such a step only refreshes
+// contacts and writes back current state. Set small nonzero `dt` to actually let
+// constraints relax, at the cost of bodies moving slightly.
+#[derive(Reflect, Resource)]
+#[reflect(Resource)]
+pub struct ConstraintRelaxation {
+    pub key: Option<KeyCode>,
+    pub iterations: u32,
+    pub dt: f32,
+}
+
+impl Default for ConstraintRelaxation {
+    fn default() -> Self {
+        Self {
+            key: Some(KeyCode::F11),
+            iterations: 10,
+            dt: 0.,
+        }
+    }
+}
+
+// After every tick, casts a ray along the path of each fast body since previous tick,
+// and warns if it crossed a collider without being in contact with it.
+#[derive(Reflect, Resource)]
+#[reflect(Resource)]
+pub struct TunnelingDetector {
+    pub enabled: bool,
+    // bodies slower than this (m/s) are not checked
+    pub min_speed: f32,
+}
+
+impl Default for TunnelingDetector {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            min_speed: 10.,
+        }
+    }
+}
+
+// logged by `DebugPlugin`, other readers can e.g. highlight the bodies
+#[derive(Event, Debug, Clone, Copy)]
+pub struct TunnelingWarning {
+    pub entity: Entity,
+    // collider the body passed through
+    pub other: Entity,
+    pub speed: f32,
+}
+
+// Hold key to run simulation ahead, on release bodies and clock are restored to where they
+// were when key was pressed, and previous mode is resumed.
+#[derive(Reflect, Resource)]
+#[reflect(Resource)]
+pub struct PeekSettings {
+    pub key: Option<KeyCode>,
+    pub speed: f32,
+}
+
+impl Default for PeekSettings {
+    fn default() -> Self {
+        Self {
+            key: Some(KeyCode::Comma),
+            speed: 1.,
+        }
+    }
+}
+
+// snapshot and mode from when peeking started
+#[derive(Resource, Default)]
+struct PeekState(Option<(BodySnapshot, PhysicsTimeMode)>);
+
+// marks where each dynamic body last touched a fixed body (the floor), fading out after contact ends
+#[derive(Reflect, Resource)]
+#[reflect(Resource)]
+pub struct ShowGroundContacts {
+    pub enabled: bool,
+    // seconds of real time
+    pub fade_time: f32,
+    // latest contact point per body and real time when it was seen
+    #[reflect(ignore)]
+    marks: HashMap<Entity, (Vec3, f32)>,
+}
+
+impl Default for ShowGroundContacts {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            fade_time: 1.,
+            marks: HashMap::new(),
+        }
+    }
+}
+
+// Verifies that replaying from the `LoopRegion` snapshot matches forward integration:
+// positions from the first pass through the region are kept as reference, and every later
+// pass is compared against them, max deviation is logged when the pass reaches the end.
+// Reference is dropped when the region changes or the check is disabled.
+#[derive(Reflect, Resource)]
+#[reflect(Resource)]
+pub struct ReplayFidelityCheck {
+    pub enabled: bool,
+    // draw reference positions next to replayed bodies
+    pub overlay: bool,
+    // max deviation (m) of the last completed replay pass
+    pub last_max_deviation: Option<f32>,
+    #[reflect(ignore)]
+    reference: HashMap<u64, Vec<(Entity, Vec3)>>,
+    #[reflect(ignore)]
+    current_max_deviation: Option<f32>,
+    #[reflect(ignore)]
+    overlay_positions: Vec<Vec3>,
+}
+
+impl Default for ReplayFidelityCheck {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            overlay: true,
+            last_max_deviation: None,
+            reference: HashMap::new(),
+            current_max_deviation: None,
+            overlay_positions: Vec::new(),
+        }
+    }
+}
+
+#[derive(ScheduleLabel, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct RelaxSchedule;
+
+fn peek(world: &mut World) {
+    let settings = world.resource::<PeekSettings>();
+    let Some(key) = settings.key else { return; };
+    let speed = settings.speed;
+    let keys = world.resource::<Input<KeyCode>>();
+    // release is still handled while typing, so the key can't get stuck
+    let pressed = keys.just_pressed(key) && !world.resource::<crate::KeyboardCaptured>().0;
+    let released = keys.just_released(key);
+
+    if pressed && world.resource::<PeekState>().0.is_none() {
+        let snapshot = BodySnapshot::capture(world);
+        let mut time = world.resource_mut::<PhysicsTime>();
+        let mode = time.context().mode;
+        // overridden, so `old_mode` (used by resume) stays intact
+        time.context_mut().override_mode(PhysicsTimeMode::Running { speed });
+        world.resource_mut::<PeekState>().0 = Some((snapshot, mode));
+    }
+
+    if released {
+        let Some((snapshot, mode)) = world.resource_mut::<PeekState>().0.take() else { return; };
+        snapshot.restore(world);
+        let mut time = world.resource_mut::<PhysicsTime>();
+        let context = time.context_mut();
+        context.override_mode(mode);
+        context.overstep = Duration::ZERO;
+    }
+}
+
+fn relax_constraints(world: &mut World) {
+    let settings = world.resource::<ConstraintRelaxation>();
+    let Some(key) = settings.key else { return; };
+    if !world.resource::<Input<KeyCode>>().just_pressed(key) { return; }
+
+    let iterations = settings.iterations;
+    let dt = settings.dt;
+
+    if !world.resource::<PhysicsTime>().is_paused() {
+        warn!("constraint relaxation is only available while simulation is paused");
+        return;
+    }
+
+    let mut config = world.resource_mut::<RapierConfiguration>();
+    let timestep_mode = config.timestep_mode;
+    config.timestep_mode = TimestepMode::Fixed { dt, substeps: 1 };
+
+    for _ in 0..iterations {
+        world.run_schedule(RelaxSchedule);
+    }
+
+    world.resource_mut::<RapierConfiguration>().timestep_mode = timestep_mode;
+}
+
+fn dump_contacts(
+    settings: Res<ContactDebugSettings>,
+    keys: Res<Input<KeyCode>>,
+    time: Res<PhysicsTime>,
+    context: Res<RapierContext>,
+) {
+    let Some(key) = settings.dump_key else { return; };
+    if !keys.just_pressed(key) { return; }
+
+    if !time.is_paused() {
+        warn!("contact dump is only available while simulation is paused");
+        return;
+    }
+
+    info!("contacts at {:?}:", time.elapsed());
+    for pair in context.contact_pairs() {
+        if !pair.has_any_active_contacts() { continue; }
+
+        for manifold in pair.manifolds() {
+            let points = manifold.solver_contacts().map(|contact| contact.point()).collect::<Vec<_>>();
+            let impulses = manifold.points().map(|contact| contact.impulse()).collect::<Vec<_>>();
+            info!(
+                "  {:?} - {:?}: normal={} points={:?} impulses={:?}",
+                pair.collider1(),
+                pair.collider2(),
+                manifold.normal(),
+                points,
+                impulses,
+            );
+        }
+    }
+}
+
+fn draw_contacts(
+    mut gizmos: Gizmos,
+    settings: Res<ContactDebugSettings>,
+    context: Res<RapierContext>,
+) {
+    if !settings.draw { return; }
+
+    for pair in context.contact_pairs() {
+        for manifold in pair.manifolds() {
+            let normal = manifold.normal();
+            for contact in manifold.solver_contacts() {
+                gizmos.sphere(contact.point(), Quat::IDENTITY, 0.03, Color::YELLOW);
+                gizmos.ray(contact.point(), normal * 0.3, Color::YELLOW);
+            }
+        }
+    }
+}
+
+// velocity is only written back by physics ticks, so arrows stay still while paused
+fn draw_velocities(
+    mut gizmos: Gizmos,
+    settings: Res<ShowVelocityGizmos>,
+    bodies: Query<(&GlobalTransform, &Velocity)>,
+) {
+    if !settings.enabled { return; }
+
+    for (transform, velocity) in bodies.iter() {
+        let speed = velocity.linvel.length();
+        if speed <= f32::EPSILON { continue; }
+
+        let t = (speed / settings.max_speed).clamp(0., 1.);
+        let color = Color::rgb(t, 1. - t, 0.);
+
+        let start = transform.translation();
+        let end = start + velocity.linvel * settings.scale;
+        gizmos.line(start, end, color);
+
+        // arrow head
+        let direction = velocity.linvel / speed;
+        let side = direction.any_orthonormal_vector();
+        let head_length = (speed * settings.scale * 0.2).min(0.3);
+        gizmos.line(end, end - (direction - side * 0.5) * head_length, color);
+        gizmos.line(end, end - (direction + side * 0.5) * head_length, color);
+    }
+}
+
+fn detect_tunneling(
+    detector: Res<TunnelingDetector>,
+    context: Res<RapierContext>,
+    bodies: Query<(Entity, &Transform, &Velocity)>,
+    mut previous: Local<HashMap<Entity, Vec3>>,
+    mut warnings: EventWriter<TunnelingWarning>,
+) {
+    if !detector.enabled {
+        previous.clear();
+        return;
+    }
+
+    let mut current = HashMap::with_capacity(previous.len());
+    for (entity, transform, velocity) in bodies.iter() {
+        let position = transform.translation;
+        current.insert(entity, position);
+
+        if velocity.linvel.length() < detector.min_speed { continue; }
+        let Some(&old_position) = previous.get(&entity) else { continue; };
+        let path = position - old_position;
+        let distance = path.length();
+        if distance == 0. { continue; }
+
+        let filter = QueryFilter::default().exclude_sensors().exclude_rigid_body(entity);
+        let Some((other, _toi)) = context.cast_ray(old_position, path / distance, distance, true, filter) else { continue; };
+        let touching = context.contact_pair(entity, other).map_or(false, |pair| pair.has_any_active_contacts());
+        if touching { continue; }
+
+        warnings.send(TunnelingWarning { entity, other, speed: velocity.linvel.length() });
+    }
+    *previous = current;
+}
+
+fn log_tunneling(mut warnings: EventReader<TunnelingWarning>) {
+    for warning in warnings.read() {
+        warn!("{:?} passed through {:?} at {:.1} m/s, consider more substeps or CCD", warning.entity, warning.other, warning.speed);
+    }
+}
+
+fn check_replay_fidelity(
+    mut check: ResMut<ReplayFidelityCheck>,
+    region: Res<LoopRegion>,
+    time: Res<PhysicsTime>,
+    bodies: Query<(Entity, &Transform, &RigidBody)>,
+) {
+    if !check.enabled || !region.enabled || region.is_changed() {
+        if !check.reference.is_empty() {
+            check.reference.clear();
+            check.current_max_deviation = None;
+            check.overlay_positions.clear();
+        }
+        return;
+    }
+
+    let tick = time.elapsed_ticks();
+    if tick <= region.start_tick || tick > region.end_tick { return; }
+
+    let positions = bodies.iter()
+        .filter(|(.., body)| matches!(body, RigidBody::Dynamic))
+        .map(|(entity, transform, _)| (entity, transform.translation))
+        .collect::<Vec<_>>();
+
+    let Some(reference) = check.reference.get(&tick) else {
+        check.reference.insert(tick, positions);
+        return;
+    };
+
+    let mut max_deviation = 0f32;
+    let mut overlay_positions = Vec::with_capacity(reference.len());
+    for (entity, expected) in reference.iter() {
+        let Some((_, actual)) = positions.iter().find(|(e, _)| e == entity) else { continue; };
+        max_deviation = max_deviation.max(actual.distance(*expected));
+        overlay_positions.push(*expected);
+    }
+    check.overlay_positions = overlay_positions;
+    let max_deviation = check.current_max_deviation.map_or(max_deviation, |d| d.max(max_deviation));
+    check.current_max_deviation = Some(max_deviation);
+
+    if tick == region.end_tick {
+        info!(
+            "replay of ticks {}..{} from snapshot deviates from forward run by {:.6} m",
+            region.start_tick, region.end_tick, max_deviation,
+        );
+        check.last_max_deviation = Some(max_deviation);
+        check.current_max_deviation = None;
+    }
+}
+
+fn draw_replay_reference(mut gizmos: Gizmos, check: Res<ReplayFidelityCheck>) {
+    if !check.enabled || !check.overlay { return; }
+
+    for position in check.overlay_positions.iter() {
+        gizmos.sphere(*position, Quat::IDENTITY, 0.1, Color::CYAN);
+    }
+}
+
+// collected every tick, so short bounces during fast-forward aren't missed
+fn collect_ground_contacts(
+    mut settings: ResMut<ShowGroundContacts>,
+    context: Res<RapierContext>,
+    bodies: Query<&RigidBody>,
+    real_time: Res<Time<Real>>,
+) {
+    if !settings.enabled { return; }
+    let now = real_time.elapsed_seconds();
+
+    for pair in context.contact_pairs() {
+        if !pair.has_any_active_contacts() { continue; }
+        let (a, b) = (pair.collider1(), pair.collider2());
+        let body = match (bodies.get(a), bodies.get(b)) {
+            (Ok(RigidBody::Dynamic), Ok(RigidBody::Fixed)) => a,
+            (Ok(RigidBody::Fixed), Ok(RigidBody::Dynamic)) => b,
+            _ => continue,
+        };
+
+        let point = pair.manifolds()
+            .find_map(|manifold| manifold.solver_contacts().next().map(|contact| contact.point()));
+        if let Some(point) = point {
+            settings.marks.insert(body, (point, now));
+        }
+    }
+}
+
+fn draw_ground_contacts(
+    mut gizmos: Gizmos,
+    mut settings: ResMut<ShowGroundContacts>,
+    real_time: Res<Time<Real>>,
+) {
+    if !settings.enabled {
+        if !settings.marks.is_empty() { settings.marks.clear(); }
+        return;
+    }
+
+    let now = real_time.elapsed_seconds();
+    let fade_time = settings.fade_time.max(f32::EPSILON);
+    settings.marks.retain(|_, (_, seen)| now - *seen < fade_time);
+
+    for (point, seen) in settings.marks.values() {
+        let alpha = 1. - (now - seen) / fade_time;
+        let color = Color::ORANGE_RED.with_a(alpha);
+        gizmos.circle(*point + Vec3::Y * 0.01, Vec3::Y, 0.15, color);
+        gizmos.sphere(*point, Quat::IDENTITY, 0.04, color);
+    }
+}
@@ -0,0 +1,161 @@
+// ctrl+click on a body to render it in highlight color, useful to track it among many,
+// highlighted bodies can then be simulated in isolation
+
+use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+#[cfg(feature = "ui")]
+use bevy_inspector_egui::bevy_egui::EguiContexts;
+use bevy_rapier3d::prelude::*;
+
+use crate::time::SimDisabled;
+
+pub struct HighlightPlugin;
+
+impl Plugin for HighlightPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .register_type::<Highlighted>()
+            .init_resource::<IsolateSettings>()
+            .register_type::<IsolateSettings>()
+            .add_systems(Startup, init_highlight_material)
+            .add_systems(Update, (
+                toggle_highlight_on_click,
+                apply_highlight_material.after(toggle_highlight_on_click),
+                toggle_isolation.run_if(crate::keyboard_free),
+            ));
+    }
+}
+
+#[derive(Component, Reflect, Default)]
+#[reflect(Component)]
+pub struct Highlighted;
+
+// key to freeze all bodies except highlighted ones, press again to unfreeze
+#[derive(Reflect, Resource)]
+#[reflect(Resource)]
+pub struct IsolateSettings {
+    pub key: Option<KeyCode>,
+}
+
+impl Default for IsolateSettings {
+    fn default() -> Self {
+        Self { key: Some(KeyCode::I) }
+    }
+}
+
+// material the body had before being highlighted
+#[derive(Component)]
+struct OriginalMaterial(Handle<StandardMaterial>);
+
+#[derive(Resource)]
+struct HighlightMaterial(Handle<StandardMaterial>);
+
+fn init_highlight_material(mut commands: Commands, mut materials: ResMut<Assets<StandardMaterial>>) {
+    commands.insert_resource(HighlightMaterial(materials.add(Color::rgb_u8(255, 200, 60).into())));
+}
+
+#[allow(clippy::too_many_arguments)]
+fn toggle_highlight_on_click(
+    mut commands: Commands,
+    #[cfg(feature = "ui")]
+    mut egui_contexts: EguiContexts,
+    buttons: Res<Input<MouseButton>>,
+    keys: Res<Input<KeyCode>>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    cameras: Query<(&Camera, &GlobalTransform)>,
+    context: Res<RapierContext>,
+    highlighted: Query<(), With<Highlighted>>,
+) {
+    if !buttons.just_pressed(MouseButton::Left) { return; }
+    if !keys.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]) { return; }
+    #[cfg(feature = "ui")]
+    if egui_contexts.ctx_mut().wants_pointer_input() { return; }
+
+    let Ok(window) = windows.get_single() else { return; };
+    let Some(cursor) = window.cursor_position() else { return; };
+
+    for (camera, camera_transform) in cameras.iter() {
+        let Some(ray) = camera.viewport_to_world(camera_transform, cursor) else { continue; };
+        let hit = context.cast_ray(ray.origin, ray.direction, f32::MAX, true, QueryFilter::only_dynamic());
+        let Some((entity, _toi)) = hit else { continue; };
+
+        if highlighted.contains(entity) {
+            commands.entity(entity).remove::<Highlighted>();
+        } else {
+            commands.entity(entity).insert(Highlighted);
+        }
+        break;
+    }
+}
+
+fn apply_highlight_material(
+    mut commands: Commands,
+    highlight_material: Res<HighlightMaterial>,
+    mut added: Query<(Entity, &mut Handle<StandardMaterial>), Added<Highlighted>>,
+    mut restored: Query<(&mut Handle<StandardMaterial>, &OriginalMaterial), Without<Highlighted>>,
+    mut removed: RemovedComponents<Highlighted>,
+) {
+    for (entity, mut material) in added.iter_mut() {
+        commands.entity(entity).insert(OriginalMaterial(material.clone()));
+        *material = highlight_material.0.clone();
+    }
+
+    for entity in removed.read() {
+        let Ok((mut material, original)) = restored.get_mut(entity) else { continue; };
+        *material = original.0.clone();
+        commands.entity(entity).remove::<OriginalMaterial>();
+    }
+}
+
+fn toggle_isolation(
+    mut commands: Commands,
+    settings: Res<IsolateSettings>,
+    keys: Res<Input<KeyCode>>,
+    disabled: Query<Entity, With<SimDisabled>>,
+    bodies: Query<(Entity, &RigidBody), Without<Highlighted>>,
+) {
+    let Some(key) = settings.key else { return; };
+    if !keys.just_pressed(key) { return; }
+
+    if !disabled.is_empty() {
+        for entity in disabled.iter() {
+            commands.entity(entity).remove::<SimDisabled>();
+        }
+        return;
+    }
+
+    for (entity, body) in bodies.iter() {
+        if matches!(body, RigidBody::Dynamic) {
+            commands.entity(entity).insert(SimDisabled);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::KeyboardCaptured;
+
+    #[test]
+    fn isolation_key_is_ignored_while_typing() {
+        let mut app = App::new();
+        app
+            .init_resource::<Input<KeyCode>>()
+            .init_resource::<IsolateSettings>()
+            .init_resource::<KeyboardCaptured>()
+            .add_systems(Update, toggle_isolation.run_if(crate::keyboard_free));
+        let body = app.world.spawn(RigidBody::Dynamic).id();
+
+        app.world.insert_resource(KeyboardCaptured(true));
+        app.world.resource_mut::<Input<KeyCode>>().press(KeyCode::I);
+        app.update();
+        assert!(!app.world.entity(body).contains::<SimDisabled>());
+
+        app.world.insert_resource(KeyboardCaptured(false));
+        let mut keys = app.world.resource_mut::<Input<KeyCode>>();
+        keys.reset(KeyCode::I);
+        keys.press(KeyCode::I);
+        app.update();
+        assert!(app.world.entity(body).contains::<SimDisabled>());
+    }
+}
@@ -0,0 +1,4 @@
+// Simulation time control without the demo app, usable headless with `default-features = false`.
+pub mod recording;
+pub mod rng;
+pub mod time;
@@ -1,70 +1,196 @@
 use std::time::Duration;
 
 use bevy::diagnostic::{FrameTimeDiagnosticsPlugin, LogDiagnosticsPlugin};
+#[cfg(feature = "ui")]
 use bevy::input::common_conditions::input_toggle_active;
 use bevy::prelude::*;
+use bevy::winit::{UpdateMode, WinitSettings};
+#[cfg(feature = "ui")]
 use bevy_inspector_egui::quick::WorldInspectorPlugin;
 use bevy_rapier3d::prelude::*;
-use time::PhysicsTimeExt;
+use bevy_rapier_sim_time::{recording, rng, time};
+use scene::{SceneDescription, ScenePath, ShapeDescription};
+use time::{PhysicsSchedule, PhysicsTimeExt, RestartSchedule, SimRestart, SpawnedBySim};
 
 mod camera;
-mod time;
+mod debug;
+mod highlight;
+mod metronome;
+mod mode_log;
+mod scene;
+mod settle;
+mod stats;
+#[cfg(feature = "ui")]
 mod ui;
 
-#[derive(Event)]
-struct RestartEvent;
-
 fn main() {
-    App::new()
+    let mut app = App::new();
+    app
         .add_plugins((
             DefaultPlugins,
-            WorldInspectorPlugin::new().run_if(input_toggle_active(false, KeyCode::F12)),
             RapierPhysicsPlugin::<NoUserData>::default().with_default_system_setup(false),
-            //RapierDebugRenderPlugin::default(),
+            RapierDebugRenderPlugin { enabled: false, ..default() },
             camera::OrbitCameraPlugin,
-            time::TimePlugin,
-            ui::UiPlugin,
+            debug::DebugPlugin,
+            highlight::HighlightPlugin,
+            metronome::MetronomePlugin,
+            mode_log::ModeLogPlugin,
+            recording::RecordingPlugin,
+            scene::ScenePlugin,
+            settle::SettlePlugin,
+            stats::StatsPlugin,
+            time::TimePlugin::default(),
+        ))
+        .init_resource::<rng::SimRng>()
+        .insert_resource(ScenePath(std::env::args_os().nth(1).map(Into::into)))
+        .init_resource::<GravityScale>()
+        .init_resource::<KeyboardCaptured>()
+        .register_type::<GravityScale>()
+        .init_resource::<StateDumpSettings>()
+        .init_resource::<RestartSnapshot>()
+        .register_type::<RestartSnapshot>()
+        .init_resource::<PreRoll>()
+        .register_type::<PreRoll>()
+        .register_type::<StateDumpSettings>()
+        .add_systems(Startup, (spawn_scene, seek_from_env))
+        .add_systems(RestartSchedule, reset_scene)
+        .add_systems(PhysicsSchedule,
+            capture_restart_snapshot.after(PhysicsSet::SyncBackend).before(PhysicsSet::StepSimulation),
+        )
+        // pre-roll ticks count towards the target of `SimRestart::seek`
+        .add_systems(PreUpdate, pre_roll.after(time::restart_simulation).before(time::start_restart_seek))
+        .add_systems(Update, (
+            bevy::window::close_on_esc,
+            dump_state.run_if(keyboard_free),
+            apply_gravity_scale,
+            throttle_rendering,
         ))
-        .add_event::<RestartEvent>()
-        .add_systems(Startup, spawn_scene)
-        .add_systems(PreUpdate, reset_scene.before(time::run_physics_schedule))
-        .add_systems(Update, bevy::window::close_on_esc)
         .add_plugins(FrameTimeDiagnosticsPlugin)
         .add_plugins(LogDiagnosticsPlugin {
             wait_duration: Duration::from_millis(1000),
             filter: Some(vec![FrameTimeDiagnosticsPlugin::FPS]),
             ..default()
         })
-        .add_systems(time::PhysicsSchedule, (
-            RapierPhysicsPlugin::<NoUserData>::get_systems(PhysicsSet::SyncBackend)
-                .in_set(PhysicsSet::SyncBackend),
-            RapierPhysicsPlugin::<NoUserData>::get_systems(PhysicsSet::StepSimulation)
-                .in_set(PhysicsSet::StepSimulation),
-            RapierPhysicsPlugin::<NoUserData>::get_systems(PhysicsSet::Writeback)
-                .in_set(PhysicsSet::Writeback),
-        ))
-        .add_systems(Last, bevy_rapier3d::plugin::systems::sync_removals)
-        .insert_resource(ClearColor(Color::BLACK))
-        .insert_resource(RapierConfiguration {
-            timestep_mode: TimestepMode::Fixed {
-                dt: time::DEFAULT_TIMESTEP.as_secs_f32(),
-                substeps: 1,
-            },
-            ..default()
-        })
-        .run();
+        .insert_resource(ClearColor(Color::BLACK));
+
+    #[cfg(feature = "ui")]
+    app.add_plugins((
+        WorldInspectorPlugin::new().run_if(input_toggle_active(false, KeyCode::F12)),
+        ui::UiPlugin,
+    ));
+
+    app.run();
 }
 
 #[derive(Component)]
 struct Ball;
 
+// Set while egui has keyboard focus (e.g. a text field is being edited), hotkey systems
+// run with `keyboard_free`, so typing doesn't trigger them. Always false without `ui` feature.
+#[derive(Resource, Debug, Default, Clone, Copy, PartialEq, Eq)]
+struct KeyboardCaptured(bool);
+
+fn keyboard_free(captured: Res<KeyboardCaptured>) -> bool {
+    !captured.0
+}
+
+// global gravity multiplier, applied live to rapier configuration
+#[derive(Reflect, Resource)]
+#[reflect(Resource)]
+struct GravityScale {
+    base: Vec3,
+    scale: f32,
+}
+
+impl Default for GravityScale {
+    fn default() -> Self {
+        Self {
+            base: Vec3::Y * -9.81,
+            scale: 1.,
+        }
+    }
+}
+
+#[derive(Reflect, Resource)]
+#[reflect(Resource)]
+struct StateDumpSettings {
+    key: Option<KeyCode>,
+}
+
+impl Default for StateDumpSettings {
+    fn default() -> Self {
+        Self { key: Some(KeyCode::F9) }
+    }
+}
+
+// Restart by restoring state of existing bodies captured on the first tick after they were
+// spawned, instead of respawning them, which is faster and gives exactly the same initial
+// conditions. Snapshot is discarded if the scene was changed (or reloaded from `ScenePath`
+// with different contents), seed was changed or any body was despawned.
+// Bodies are spawned without `SpawnedBySim` while enabled, so restart keeps them.
+#[derive(Reflect, Resource)]
+#[reflect(Resource)]
+struct RestartSnapshot {
+    enabled: bool,
+    #[reflect(ignore)]
+    bodies: Vec<(Entity, Transform, Velocity)>,
+    seed: u64,
+    // bodies were respawned, captured on the next tick
+    #[reflect(ignore)]
+    capture_pending: bool,
+}
+
+impl Default for RestartSnapshot {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            bodies: Vec::new(),
+            seed: 0,
+            capture_pending: false,
+        }
+    }
+}
+
+// Ticks run right after every restart before the scene is shown, regardless of mode,
+// so it starts mid-motion. Spawning is seeded, so pre-rolled state is reproducible.
+#[derive(Reflect, Resource)]
+#[reflect(Resource)]
+struct PreRoll {
+    ticks: u32,
+    #[reflect(ignore)]
+    pending: bool,
+}
+
+impl Default for PreRoll {
+    fn default() -> Self {
+        Self {
+            ticks: 0,
+            pending: false,
+        }
+    }
+}
+
+// SIM_SEEK=<seed>:<tick> restarts with given seed and fast-forwards to given tick on launch
+fn seek_from_env(mut events: EventWriter<SimRestart>) {
+    let Some(value) = std::env::var_os("SIM_SEEK") else { return; };
+    let parsed = value.to_str()
+        .and_then(|value| value.split_once(':'))
+        .and_then(|(seed, tick)| Some((seed.trim().parse().ok()?, tick.trim().parse().ok()?)));
+    let Some((seed, target_tick)) = parsed else {
+        error!("SIM_SEEK should be <seed>:<tick>, got {:?}", value);
+        return;
+    };
+    events.send(SimRestart::seek(seed, target_tick));
+}
+
 fn spawn_scene(
     mut commands: Commands,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<StandardMaterial>>,
-    mut events: EventWriter<RestartEvent>,
+    mut events: EventWriter<SimRestart>,
+    camera_settings: Res<camera::OrbitCameraSettings>,
 ) {
-    events.send(RestartEvent);
+    events.send(SimRestart::default());
 
     // circular base
     commands.spawn((
@@ -90,50 +216,176 @@ fn spawn_scene(
         ..default()
     });
     // camera
-    let expected_transform = Transform::from_xyz(-5., 7.5, 16.).looking_at(Vec3::Y, Vec3::Y);
-    let (yaw, pitch, _roll) = expected_transform.rotation.to_euler(EulerRot::YXZ);
     commands.spawn(camera::OrbitCameraBundle {
-        orbit_camera: camera::OrbitCamera {
-            gimbal_x: -yaw,
-            gimbal_y: -pitch,
-            distance: expected_transform.translation.length(),
-            ..default()
-        },
+        orbit_camera: camera::OrbitCamera::from_transform(camera_settings.initial_transform),
         ..default()
     });
 }
 
+#[allow(clippy::too_many_arguments)]
 fn reset_scene(
     mut commands: Commands,
-    mut time: ResMut<time::PhysicsTime>,
-    mut events: EventReader<RestartEvent>,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<StandardMaterial>>,
-    mut balls: Query<Entity, With<Ball>>,
+    mut balls: Query<(Entity, &mut Transform, &mut Velocity), With<Ball>>,
+    mut scene: ResMut<SceneDescription>,
+    scene_path: Res<ScenePath>,
+    mut rng: ResMut<rng::SimRng>,
+    mut snapshot: ResMut<RestartSnapshot>,
+    mut pre_roll: ResMut<PreRoll>,
+    request: Res<time::RestartRequest>,
 ) {
-    if events.is_empty() { return; }
-    events.clear();
+    pre_roll.pending = pre_roll.ticks > 0;
+    if let Some(seed) = request.0.seed {
+        rng.set_seed(seed);
+    }
 
-    *time = time::PhysicsTime::default();
-    time.resume();
+    if let Some(path) = &scene_path.0 {
+        match SceneDescription::load(path) {
+            Ok(loaded) => { scene.set_if_neq(loaded); }
+            Err(err) => error!("failed to load scene from {}: {}", path.display(), err),
+        }
+    }
 
-    for entity in balls.iter_mut() {
-        commands.entity(entity).despawn();
+    let snapshot_valid = snapshot.enabled
+        && !scene.is_changed()
+        && !snapshot.bodies.is_empty()
+        && snapshot.seed == rng.seed()
+        && snapshot.bodies.len() == balls.iter().len()
+        && snapshot.bodies.iter().all(|(entity, ..)| balls.contains(*entity));
+
+    if snapshot_valid {
+        for (entity, transform, velocity) in snapshot.bodies.iter() {
+            let Ok((_, mut current_transform, mut current_velocity)) = balls.get_mut(*entity) else { continue; };
+            *current_transform = *transform;
+            *current_velocity = *velocity;
+        }
+        return;
     }
 
-    commands.spawn((
-        PbrBundle {
-            mesh: meshes.add(Mesh::from(shape::UVSphere {
-                radius: 0.5,
+    snapshot.bodies.clear();
+    snapshot.seed = rng.seed();
+    snapshot.capture_pending = snapshot.enabled;
+
+    // bodies kept for the snapshot, the rest was despawned with `SpawnedBySim`
+    for (entity, ..) in balls.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    rng.reseed();
+
+    for (index, body) in scene.bodies.iter().enumerate() {
+        let jitter = Vec3::new(rng.next_signed(), rng.next_signed(), rng.next_signed()) * body.position_jitter;
+        let (mesh, collider) = match body.shape {
+            ShapeDescription::Ball { radius } => (
+                Mesh::from(shape::UVSphere { radius, ..default() }),
+                Collider::ball(radius),
+            ),
+            ShapeDescription::Cuboid { half_extents: [x, y, z] } => (
+                Mesh::from(shape::Box::new(x * 2., y * 2., z * 2.)),
+                Collider::cuboid(x, y, z),
+            ),
+        };
+
+        let transform = Transform::from_translation(Vec3::from(body.position) + jitter);
+        let velocity = Velocity::linear(body.velocity.into());
+        let mut entity = commands.spawn((
+            PbrBundle {
+                mesh: meshes.add(mesh),
+                material: materials.add(Color::rgb_u8(124, 144, 255).into()),
+                transform,
                 ..default()
-            })),
-            material: materials.add(Color::rgb_u8(124, 144, 255).into()),
-            transform: Transform::from_xyz(0., 4., 0.),
-            ..default()
-        },
-        RigidBody::Dynamic,
-        Collider::ball(0.5),
-        Restitution::coefficient(0.9),
-        Ball,
-    ));
+            },
+            RigidBody::Dynamic,
+            collider,
+            Restitution::coefficient(body.restitution),
+            velocity,
+            Ball,
+            recording::RecordingId(index as u32),
+        ));
+
+        if !snapshot.enabled {
+            entity.insert(SpawnedBySim);
+        }
+    }
+}
+
+// after rapier created the bodies, before they're moved
+fn capture_restart_snapshot(
+    mut snapshot: ResMut<RestartSnapshot>,
+    balls: Query<(Entity, &Transform, &Velocity), With<Ball>>,
+) {
+    if !snapshot.capture_pending { return; }
+    snapshot.capture_pending = false;
+    snapshot.bodies = balls.iter().map(|(entity, transform, velocity)| (entity, *transform, *velocity)).collect();
+}
+
+fn pre_roll(world: &mut World) {
+    let mut pre_roll = world.resource_mut::<PreRoll>();
+    if !pre_roll.pending { return; }
+    pre_roll.pending = false;
+
+    let ticks = pre_roll.ticks;
+    for _ in 0..ticks {
+        time::force_single_step(world);
+    }
+}
+
+// While fast-forward is throttled (see `FastForwardThrottle`), winit waits for a whole frame
+// at render rate instead of redrawing continuously. Previous update modes are restored after.
+fn throttle_rendering(
+    time: Res<time::PhysicsTime>,
+    throttle: Res<time::FastForwardThrottle>,
+    winit: Option<ResMut<WinitSettings>>,
+    mut saved_modes: Local<Option<(UpdateMode, UpdateMode)>>,
+) {
+    let Some(mut winit) = winit else { return; };
+    let fast_forward = matches!(time.context().mode, time::PhysicsTimeMode::Running { speed } if speed.is_infinite());
+
+    if fast_forward && throttle.enabled && throttle.render_fps > 0. {
+        if saved_modes.is_none() {
+            *saved_modes = Some((winit.focused_mode, winit.unfocused_mode));
+        }
+        let mode = UpdateMode::ReactiveLowPower { wait: Duration::from_secs_f32(throttle.render_fps.recip()) };
+        if winit.focused_mode != mode || winit.unfocused_mode != mode {
+            winit.focused_mode = mode;
+            winit.unfocused_mode = mode;
+        }
+    } else if let Some((focused_mode, unfocused_mode)) = saved_modes.take() {
+        winit.focused_mode = focused_mode;
+        winit.unfocused_mode = unfocused_mode;
+    }
+}
+
+fn apply_gravity_scale(gravity: Res<GravityScale>, mut config: ResMut<RapierConfiguration>) {
+    if !gravity.is_changed() { return; }
+    config.gravity = gravity.base * gravity.scale;
+}
+
+// log everything needed to reproduce current state in one line, for bug reports
+fn dump_state(
+    settings: Res<StateDumpSettings>,
+    keys: Res<Input<KeyCode>>,
+    time: Res<time::PhysicsTime>,
+    balls: Query<(&Transform, &Velocity), With<Ball>>,
+) {
+    let Some(key) = settings.key else { return; };
+    if !keys.just_pressed(key) { return; }
+
+    let mut line = format!(
+        "elapsed={:.6}s ticks={} mode={} {:?}",
+        time.elapsed_secs_f64(),
+        time.elapsed_ticks(),
+        time.context().mode,
+        time.context(),
+    );
+    for (transform, velocity) in balls.iter() {
+        line += &format!(
+            " ball(pos={} linvel={} angvel={})",
+            transform.translation,
+            velocity.linvel,
+            velocity.angvel,
+        );
+    }
+    info!("state dump: {}", line);
 }
@@ -0,0 +1,71 @@
+// audible click on executed physics ticks, helps to perceive simulation rate
+
+use std::time::Duration;
+
+use bevy::audio::Volume;
+use bevy::prelude::*;
+
+use crate::time::{run_physics_schedule, PhysicsSchedule};
+
+pub struct MetronomePlugin;
+
+impl Plugin for MetronomePlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .register_type::<Metronome>()
+            .init_resource::<Metronome>()
+            .add_systems(PhysicsSchedule, count_metronome_ticks)
+            .add_systems(PreUpdate, play_metronome.after(run_physics_schedule));
+    }
+}
+
+#[derive(Reflect, Resource)]
+#[reflect(Resource)]
+pub struct Metronome {
+    pub enabled: bool,
+    pub volume: f32,
+    pub frequency: f32,
+    // real time between clicks, so fast-forward doesn't play thousands of them
+    pub min_interval: Duration,
+    #[reflect(ignore)]
+    pending_ticks: u32,
+}
+
+impl Default for Metronome {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            volume: 0.2,
+            frequency: 1200.,
+            min_interval: Duration::from_millis(50),
+            pending_ticks: 0,
+        }
+    }
+}
+
+fn count_metronome_ticks(mut metronome: ResMut<Metronome>) {
+    if !metronome.enabled { return; }
+    metronome.pending_ticks += 1;
+}
+
+fn play_metronome(
+    mut commands: Commands,
+    mut metronome: ResMut<Metronome>,
+    mut pitches: ResMut<Assets<Pitch>>,
+    time: Res<Time<Real>>,
+    mut last_click: Local<Option<Duration>>,
+) {
+    if metronome.pending_ticks == 0 { return; }
+    metronome.pending_ticks = 0;
+
+    let now = time.elapsed();
+    if let Some(last_click) = *last_click {
+        if now.saturating_sub(last_click) < metronome.min_interval { return; }
+    }
+    *last_click = Some(now);
+
+    commands.spawn(PitchBundle {
+        source: pitches.add(Pitch::new(metronome.frequency, Duration::from_millis(15))),
+        settings: PlaybackSettings::DESPAWN.with_volume(Volume::new_relative(metronome.volume)),
+    });
+}
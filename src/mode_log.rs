@@ -0,0 +1,78 @@
+// appends every physics mode change to a file, for post-mortem analysis of long sessions
+
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use bevy::prelude::*;
+
+use crate::time::{PhysicsModeChanged, PhysicsTime, PhysicsTimeExt};
+
+pub struct ModeLogPlugin;
+
+impl Plugin for ModeLogPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<ModeLog>()
+            .add_systems(Update, log_mode_changes);
+    }
+}
+
+// Disabled unless path is set, defaults to SIM_MODE_LOG environment variable.
+// Each line is `unix_ms=<ms> tick=<tick> elapsed=<secs> from=<mode> to=<mode>`,
+// file is opened for every line, so everything up to a crash is kept.
+#[derive(Resource)]
+pub struct ModeLog {
+    pub path: Option<PathBuf>,
+    failed: bool,
+}
+
+impl Default for ModeLog {
+    fn default() -> Self {
+        Self {
+            path: std::env::var_os("SIM_MODE_LOG").map(Into::into),
+            failed: false,
+        }
+    }
+}
+
+fn log_mode_changes(
+    mut log: ResMut<ModeLog>,
+    time: Res<PhysicsTime>,
+    mut events: EventReader<PhysicsModeChanged>,
+) {
+    if events.is_empty() { return; }
+    let Some(path) = &log.path else {
+        events.clear();
+        return;
+    };
+
+    // tick and elapsed are current, changes are detected once per frame anyway
+    let unix_ms = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_millis();
+    let line = events.read()
+        .map(|event| format!(
+            "unix_ms={} tick={} elapsed={:.6} from={} to={}\n",
+            unix_ms,
+            time.elapsed_ticks(),
+            time.elapsed_secs_f64(),
+            event.old,
+            event.new,
+        ))
+        .collect::<String>();
+
+    let result = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .and_then(|mut file| file.write_all(line.as_bytes()));
+
+    match result {
+        Ok(()) => log.failed = false,
+        // don't spam the log on every change if file is unavailable
+        Err(err) if !log.failed => {
+            error!("failed to write mode log to {}: {}", path.display(), err);
+            log.failed = true;
+        }
+        Err(_) => (),
+    }
+}
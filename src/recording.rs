@@ -0,0 +1,404 @@
+// records body trajectories per tick, so two runs can be compared
+
+use std::collections::{BTreeMap, VecDeque};
+use std::time::Duration;
+
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+
+use crate::time::{restart_for_seek, restart_simulation, PhysicsSchedule, PhysicsTime, PhysicsTimeExt, SimRestart};
+
+pub struct RecordingPlugin;
+
+impl Plugin for RecordingPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .register_type::<RecordingId>()
+            .init_resource::<TrajectoryRecorder>()
+            .add_systems(PreUpdate, reset_recording.after(restart_for_seek).before(restart_simulation))
+            .add_systems(PhysicsSchedule, record_trajectory.after(PhysicsSet::Writeback));
+    }
+}
+
+// Stable id of a body across runs, only dynamic bodies with it are recorded. Spawn order,
+// query order and entity ids may change between runs, so bodies are matched by this id instead.
+#[derive(Component, Reflect, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[reflect(Component)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+pub struct RecordingId(pub u32);
+
+// positions of recorded bodies for each executed tick, see `RecordingId`.
+// With `serialize` feature it can be stored in any serde format, e.g. as a CI baseline.
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+pub struct TrajectoryRecording {
+    pub timestep: Duration,
+    // `elapsed_ticks()` of the first stored tick, larger than 1 if older ticks were dropped
+    // or recording was enabled later
+    pub first_tick: usize,
+    pub ticks: VecDeque<TickPositions>,
+    // user notes keyed by tick
+    #[cfg_attr(feature = "serialize", serde(default))]
+    pub annotations: BTreeMap<usize, Vec<String>>,
+}
+
+// Positions of all recorded bodies in one tick, sorted by id.
+//
+// Quantized positions are stored as multiples of `step` in i16, which takes 12 instead of 16
+// bytes per body.
+// Each coordinate is off by at most `step / 2`, and is clamped to `±32767 * step`
+// (e.g. ±32m with 1mm step), so this is only suitable for scenes of limited size.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+pub enum TickPositions {
+    Full(Vec<(RecordingId, [f32; 3])>),
+    Quantized { step: f32, positions: Vec<(RecordingId, [i16; 3])> },
+}
+
+impl TickPositions {
+    pub fn new(positions: impl Iterator<Item = (RecordingId, Vec3)>, quantization: Option<f32>) -> Self {
+        let mut positions: Vec<_> = positions.collect();
+        positions.sort_by_key(|(id, _)| *id);
+        match quantization {
+            Some(step) if step > 0. => Self::Quantized {
+                step,
+                positions: positions.into_iter()
+                    .map(|(id, position)| {
+                        let q = (position / step).round().clamp(Vec3::splat(-32767.), Vec3::splat(32767.));
+                        (id, [q.x as i16, q.y as i16, q.z as i16])
+                    })
+                    .collect(),
+            },
+            _ => Self::Full(positions.into_iter().map(|(id, position)| (id, position.to_array())).collect()),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        match self {
+            Self::Full(positions) => positions.len(),
+            Self::Quantized { positions, .. } => positions.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    // in order of ids
+    pub fn iter(&self) -> impl Iterator<Item = (RecordingId, Vec3)> + '_ {
+        let (full, quantized, step): (&[(RecordingId, [f32; 3])], &[(RecordingId, [i16; 3])], f32) = match self {
+            Self::Full(positions) => (positions, &[], 0.),
+            Self::Quantized { step, positions } => (&[], positions, *step),
+        };
+        full.iter().map(|(id, p)| (*id, Vec3::from(*p)))
+            .chain(quantized.iter().map(move |(id, q)| (*id, Vec3::new(q[0] as f32, q[1] as f32, q[2] as f32) * step)))
+    }
+
+    // first body further than epsilon from its position in `other`, or missing from one of them
+    fn diff(&self, other: &Self, epsilon: f32) -> Option<(RecordingId, f32)> {
+        let mut ours = self.iter().peekable();
+        let mut theirs = other.iter().peekable();
+        loop {
+            match (ours.peek().copied(), theirs.peek().copied()) {
+                (None, None) => return None,
+                (Some((id, a)), Some((other_id, b))) if id == other_id => {
+                    let deviation = a.distance(b);
+                    if deviation > epsilon { return Some((id, deviation)); }
+                    ours.next();
+                    theirs.next();
+                }
+                (Some((id, _)), Some((other_id, _))) => return Some((id.min(other_id), f32::INFINITY)),
+                (Some((id, _)), None) | (None, Some((id, _))) => return Some((id, f32::INFINITY)),
+            }
+        }
+    }
+
+    fn memory_usage(&self) -> usize {
+        std::mem::size_of::<Self>() + match self {
+            Self::Full(positions) => positions.len() * std::mem::size_of::<(RecordingId, [f32; 3])>(),
+            Self::Quantized { positions, .. } => positions.len() * std::mem::size_of::<(RecordingId, [i16; 3])>(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DivergenceReport {
+    pub tick: usize,
+    // first diverging body, 0 if one recording has fewer ticks
+    pub body: RecordingId,
+    // infinity if body is missing from one of the recordings
+    pub deviation: f32,
+}
+
+impl TrajectoryRecording {
+    // first tick where any body is further than epsilon from its position in other recording
+    // (only ticks present in both recordings are compared)
+    pub fn diff(&self, other: &Self, epsilon: f32) -> Option<DivergenceReport> {
+        let first_tick = self.first_tick.max(other.first_tick);
+        let ours_iter = self.ticks.iter().skip(first_tick - self.first_tick);
+        let theirs_iter = other.ticks.iter().skip(first_tick - other.first_tick);
+
+        for (tick, (ours, theirs)) in (first_tick..).zip(ours_iter.zip(theirs_iter)) {
+            if let Some((body, deviation)) = ours.diff(theirs, epsilon) {
+                return Some(DivergenceReport { tick, body, deviation });
+            }
+        }
+
+        let end_tick = self.first_tick + self.ticks.len();
+        let other_end_tick = other.first_tick + other.ticks.len();
+        if end_tick != other_end_tick {
+            let tick = end_tick.min(other_end_tick);
+            return Some(DivergenceReport { tick, body: RecordingId(0), deviation: f32::INFINITY });
+        }
+
+        None
+    }
+
+    pub fn last_tick(&self) -> Option<usize> {
+        if self.ticks.is_empty() { return None; }
+        Some(self.first_tick + self.ticks.len() - 1)
+    }
+
+    // elapsed time of the first and the last recorded tick
+    pub fn time_range(&self) -> Option<(Duration, Duration)> {
+        let last_tick = self.last_tick()?;
+        let tick_time = |tick: usize| self.timestep.saturating_mul(tick.try_into().unwrap_or(u32::MAX));
+        Some((tick_time(self.first_tick), tick_time(last_tick)))
+    }
+
+    // Adds positions of given tick. Ticks run again (e.g. after step back or a seek that
+    // re-simulates from the start) replace the stored ones, so recorded range is kept.
+    // Recording starts over if ticks were skipped.
+    pub fn record(&mut self, tick: usize, positions: TickPositions) {
+        if tick < self.first_tick { return; }
+        let index = tick - self.first_tick;
+        if let Some(stored) = self.ticks.get_mut(index) {
+            *stored = positions;
+            return;
+        }
+        if index > self.ticks.len() || self.ticks.is_empty() {
+            self.ticks.clear();
+            self.first_tick = tick;
+            self.annotations.retain(|annotated, _| *annotated >= tick);
+        }
+        self.ticks.push_back(positions);
+    }
+
+    pub fn annotate(&mut self, tick: usize, note: impl Into<String>) {
+        self.annotations.entry(tick).or_default().push(note.into());
+    }
+
+    // approximate heap size in bytes, assuming body count doesn't change between ticks
+    pub fn memory_usage(&self) -> usize {
+        self.ticks.len() * self.tick_size()
+    }
+
+    // drops oldest ticks until memory usage fits, returns true if anything was dropped
+    pub fn trim_to(&mut self, max_bytes: usize) -> bool {
+        let excess = self.memory_usage().saturating_sub(max_bytes);
+        if excess == 0 { return false; }
+
+        let count = excess.div_ceil(self.tick_size()).min(self.ticks.len());
+        self.ticks.drain(..count);
+        self.first_tick += count;
+        self.annotations.retain(|tick, _| *tick >= self.first_tick);
+        true
+    }
+
+    fn tick_size(&self) -> usize {
+        self.ticks.front().map(TickPositions::memory_usage).unwrap_or(std::mem::size_of::<TickPositions>())
+    }
+}
+
+#[derive(Resource)]
+pub struct TrajectoryRecorder {
+    pub enabled: bool,
+    pub recording: TrajectoryRecording,
+    // oldest ticks are dropped when recording grows above this
+    pub memory_budget_mb: f32,
+    pub warn_on_trim: bool,
+    // store positions quantized to this step, see `TickPositions`
+    pub quantization: Option<f32>,
+    warned: bool,
+}
+
+impl TrajectoryRecorder {
+    pub fn memory_usage(&self) -> usize {
+        self.recording.memory_usage()
+    }
+}
+
+impl Default for TrajectoryRecorder {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            recording: TrajectoryRecording::default(),
+            memory_budget_mb: 64.,
+            warn_on_trim: true,
+            quantization: None,
+            warned: false,
+        }
+    }
+}
+
+// Recording is kept when restarting for a backward seek, re-simulated ticks are recorded again.
+fn reset_recording(
+    mut recorder: ResMut<TrajectoryRecorder>,
+    mut events: EventReader<SimRestart>,
+    time: Res<PhysicsTime>,
+) {
+    if events.is_empty() { return; }
+    events.clear();
+    if time.context().seek_restart_pending() { return; }
+    recorder.recording = TrajectoryRecording::default();
+    recorder.warned = false;
+}
+
+fn record_trajectory(
+    mut recorder: ResMut<TrajectoryRecorder>,
+    time: Res<PhysicsTime>,
+    bodies: Query<(&RecordingId, &Transform, &RigidBody)>,
+) {
+    if !recorder.enabled { return; }
+
+    let positions = TickPositions::new(
+        bodies.iter()
+            .filter(|(.., body)| matches!(body, RigidBody::Dynamic))
+            .map(|(id, transform, _)| (*id, transform.translation)),
+        recorder.quantization,
+    );
+
+    recorder.recording.timestep = time.context().timestep;
+    recorder.recording.record(time.elapsed_ticks() as usize, positions);
+
+    let max_bytes = (recorder.memory_budget_mb.max(0.) * 1024. * 1024.) as usize;
+    if recorder.recording.trim_to(max_bytes) && recorder.warn_on_trim && !recorder.warned {
+        recorder.warned = true;
+        warn!("trajectory recording exceeded {} MB, dropping oldest ticks", recorder.memory_budget_mb);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rng::SimRng;
+    use crate::time::TimePlugin;
+
+    // headless app running one tick per update, with a few balls dropped from seeded positions
+    fn record_run(seed: u64, ticks: u32) -> TrajectoryRecording {
+        let mut app = App::new();
+        app
+            .add_plugins((
+                MinimalPlugins,
+                TransformPlugin,
+                HierarchyPlugin,
+                RapierPhysicsPlugin::<NoUserData>::default().with_default_system_setup(false),
+                TimePlugin::minimal(),
+                RecordingPlugin,
+            ));
+
+        app.world.resource_mut::<PhysicsTime>().fixed_ticks_per_frame(1);
+        app.world.resource_mut::<TrajectoryRecorder>().enabled = true;
+
+        app.world.spawn((TransformBundle::default(), RigidBody::Fixed, Collider::cuboid(10., 0.1, 10.)));
+        let mut rng = SimRng::new(seed);
+        for id in 0..3 {
+            let position = Vec3::new(rng.next_signed(), 2. + rng.next_signed(), rng.next_signed());
+            app.world.spawn((
+                TransformBundle::from_transform(Transform::from_translation(position)),
+                RigidBody::Dynamic,
+                Collider::ball(0.2),
+                Velocity::default(),
+                RecordingId(id),
+            ));
+        }
+
+        for _ in 0..ticks {
+            app.update();
+        }
+        std::mem::take(&mut app.world.resource_mut::<TrajectoryRecorder>().recording)
+    }
+
+    #[test]
+    fn same_seed_runs_match() {
+        let baseline = record_run(7, 60);
+        let other = record_run(7, 60);
+        assert_eq!(baseline.ticks.len(), 60);
+        assert_eq!(baseline.diff(&other, 1e-6), None);
+    }
+
+    #[test]
+    fn different_seed_runs_diverge() {
+        let baseline = record_run(7, 60);
+        let other = record_run(8, 60);
+        let report = baseline.diff(&other, 1e-3).expect("runs with different seeds should diverge");
+        assert_eq!(report.tick, 1);
+    }
+
+    #[test]
+    fn quantized_positions_are_within_half_step() {
+        let step = 0.001;
+        let original = [
+            (RecordingId(0), Vec3::new(0.12345, -3.21098, 7.77777)),
+            (RecordingId(1), Vec3::new(-0.0004, 0.0006, 31.9)),
+        ];
+        let quantized = TickPositions::new(original.into_iter(), Some(step));
+        assert_eq!(quantized.len(), original.len());
+        for ((id, restored), (original_id, original)) in quantized.iter().zip(original) {
+            assert_eq!(id, original_id);
+            assert!((restored - original).abs().max_element() <= step / 2. + f32::EPSILON * 32.);
+        }
+        // only positive step quantizes
+        assert!(matches!(TickPositions::new(original.into_iter(), Some(0.)), TickPositions::Full(_)));
+        assert!(TickPositions::new(std::iter::empty(), Some(step)).is_empty());
+    }
+
+    #[test]
+    fn diff_reports_first_diverging_tick() {
+        // bodies are matched by id regardless of their order
+        let positions = |offset: f32| {
+            TickPositions::new([(RecordingId(5), Vec3::X * offset), (RecordingId(2), Vec3::ZERO)].into_iter(), None)
+        };
+        let baseline = TrajectoryRecording {
+            ticks: (0..5).map(|_| positions(1.)).collect(),
+            ..default()
+        };
+        let mut other = baseline.clone();
+        other.ticks[3] = positions(1.5);
+
+        assert_eq!(baseline.diff(&baseline, 0.), None);
+        let report = DivergenceReport { tick: 3, body: RecordingId(5), deviation: 0.5 };
+        assert_eq!(baseline.diff(&other, 0.1), Some(report));
+        // within tolerance
+        assert_eq!(baseline.diff(&other, 1.), None);
+
+        other.ticks[2] = TickPositions::new([(RecordingId(5), Vec3::X)].into_iter(), None);
+        let report = DivergenceReport { tick: 2, body: RecordingId(2), deviation: f32::INFINITY };
+        assert_eq!(baseline.diff(&other, 1.), Some(report));
+
+        other.ticks[2] = baseline.ticks[2].clone();
+        other.ticks.pop_back();
+        let report = DivergenceReport { tick: 4, body: RecordingId(0), deviation: f32::INFINITY };
+        assert_eq!(baseline.diff(&other, 1.), Some(report));
+    }
+
+    #[test]
+    fn rerun_ticks_replace_recorded_ones() {
+        let positions = |x: f32| TickPositions::new([(RecordingId(0), Vec3::X * x)].into_iter(), None);
+        let mut recording = TrajectoryRecording { timestep: Duration::from_millis(10), ..default() };
+        assert_eq!(recording.time_range(), None);
+        for tick in 1..=5 {
+            recording.record(tick, positions(tick as f32));
+        }
+        assert_eq!((recording.first_tick, recording.last_tick()), (1, Some(5)));
+
+        recording.record(3, positions(30.));
+        assert_eq!(recording.last_tick(), Some(5));
+        assert_eq!(recording.ticks[2], positions(30.));
+        assert_eq!(recording.time_range(), Some((Duration::from_millis(10), Duration::from_millis(50))));
+
+        // skipped ticks start the recording over
+        recording.record(8, positions(8.));
+        assert_eq!((recording.first_tick, recording.last_tick()), (8, Some(8)));
+    }
+}
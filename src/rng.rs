@@ -0,0 +1,80 @@
+// random numbers for scene generation, reseeded on every restart so runs can be reproduced
+
+use bevy::prelude::*;
+
+// custom generator can be plugged into `SimRng`
+pub trait SceneRng: Send + Sync + 'static {
+    fn reseed(&mut self, seed: u64);
+    fn next_u64(&mut self) -> u64;
+}
+
+// default generator, splitmix64
+#[derive(Debug, Default)]
+pub struct SplitMix64(u64);
+
+impl SceneRng for SplitMix64 {
+    fn reseed(&mut self, seed: u64) {
+        self.0 = seed;
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E3779B97F4A7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+        z ^ (z >> 31)
+    }
+}
+
+#[derive(Resource)]
+pub struct SimRng {
+    seed: u64,
+    rng: Box<dyn SceneRng>,
+}
+
+impl SimRng {
+    pub fn new(seed: u64) -> Self {
+        Self::with_rng(seed, SplitMix64::default())
+    }
+
+    pub fn with_rng(seed: u64, mut rng: impl SceneRng) -> Self {
+        rng.reseed(seed);
+        Self { seed, rng: Box::new(rng) }
+    }
+
+    // seed of the current run, enough to reproduce it
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    // takes effect on next restart
+    pub fn set_seed(&mut self, seed: u64) {
+        self.seed = seed;
+    }
+
+    // replaces the generator, it continues from the start of current seed's sequence
+    pub fn set_rng(&mut self, rng: impl SceneRng) {
+        self.rng = Box::new(rng);
+        self.rng.reseed(self.seed);
+    }
+
+    // start the sequence over, called on restart
+    pub fn reseed(&mut self) {
+        self.rng.reseed(self.seed);
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.rng.next_u64()
+    }
+
+    // uniform in [-1, 1)
+    pub fn next_signed(&mut self) -> f32 {
+        (self.next_u64() >> 40) as f32 / (1u64 << 23) as f32 - 1.
+    }
+}
+
+impl Default for SimRng {
+    fn default() -> Self {
+        Self::new(0)
+    }
+}
@@ -0,0 +1,138 @@
+// minimal data-driven scene, so test setups can be authored without recompiling
+
+use std::path::{Path, PathBuf};
+
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+use serde::{Deserialize, Serialize};
+
+pub struct ScenePlugin;
+
+impl Plugin for ScenePlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<SceneDescription>()
+            .init_resource::<SceneSaveSettings>()
+            .add_systems(Update, save_scene_on_key.run_if(crate::keyboard_free));
+    }
+}
+
+#[derive(Resource, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SceneDescription {
+    pub bodies: Vec<BodyDescription>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BodyDescription {
+    pub shape: ShapeDescription,
+    pub position: [f32; 3],
+    #[serde(default)]
+    pub velocity: [f32; 3],
+    #[serde(default)]
+    pub restitution: f32,
+    // random offset up to this distance along each axis, from `SimRng`
+    #[serde(default)]
+    pub position_jitter: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum ShapeDescription {
+    Ball { radius: f32 },
+    Cuboid { half_extents: [f32; 3] },
+}
+
+// if set, scene is re-read from this file on every restart
+#[derive(Resource, Debug, Default)]
+pub struct ScenePath(pub Option<PathBuf>);
+
+#[derive(Resource, Debug)]
+pub struct SceneSaveSettings {
+    pub key: Option<KeyCode>,
+    pub path: PathBuf,
+}
+
+impl Default for SceneSaveSettings {
+    fn default() -> Self {
+        Self {
+            key: Some(KeyCode::F5),
+            path: "scene.ron".into(),
+        }
+    }
+}
+
+impl SceneDescription {
+    pub fn load(path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        let text = std::fs::read_to_string(path)?;
+        Ok(ron::from_str(&text)?)
+    }
+}
+
+impl Default for SceneDescription {
+    fn default() -> Self {
+        Self {
+            bodies: vec![
+                BodyDescription {
+                    shape: ShapeDescription::Ball { radius: 0.5 },
+                    position: [0., 4., 0.],
+                    velocity: [0., 0., 0.],
+                    restitution: 0.9,
+                    position_jitter: 0.,
+                },
+            ],
+        }
+    }
+}
+
+// Saves current state of dynamic bodies in the format `SceneDescription::load` reads.
+//
+// Captured: position, linear velocity and restitution of dynamic bodies with ball
+// or cuboid colliders. Not captured: rotation, angular velocity, bodies with other
+// collider shapes (skipped with a warning), fixed bodies and materials.
+pub fn save_scene(world: &mut World, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    let mut query = world.query::<(
+        Entity,
+        &Transform,
+        &RigidBody,
+        &Collider,
+        Option<&Velocity>,
+        Option<&Restitution>,
+    )>();
+
+    let mut bodies = vec![];
+    for (entity, transform, body, collider, velocity, restitution) in query.iter(world) {
+        if !matches!(body, RigidBody::Dynamic) { continue; }
+
+        let shape = if let Some(ball) = collider.as_ball() {
+            ShapeDescription::Ball { radius: ball.radius() }
+        } else if let Some(cuboid) = collider.as_cuboid() {
+            ShapeDescription::Cuboid { half_extents: cuboid.half_extents().to_array() }
+        } else {
+            warn!("can't save collider shape of {:?}, skipping", entity);
+            continue;
+        };
+
+        bodies.push(BodyDescription {
+            shape,
+            position: transform.translation.to_array(),
+            velocity: velocity.map(|velocity| velocity.linvel.to_array()).unwrap_or_default(),
+            restitution: restitution.map(|restitution| restitution.coefficient).unwrap_or_default(),
+            position_jitter: 0.,
+        });
+    }
+
+    let text = ron::ser::to_string_pretty(&SceneDescription { bodies }, ron::ser::PrettyConfig::default())?;
+    std::fs::write(path, text)?;
+    Ok(())
+}
+
+fn save_scene_on_key(world: &mut World) {
+    let settings = world.resource::<SceneSaveSettings>();
+    let Some(key) = settings.key else { return; };
+    if !world.resource::<Input<KeyCode>>().just_pressed(key) { return; }
+
+    let path = settings.path.clone();
+    match save_scene(world, &path) {
+        Ok(()) => info!("scene saved to {}", path.display()),
+        Err(err) => error!("failed to save scene to {}: {}", path.display(), err),
+    }
+}
@@ -0,0 +1,122 @@
+// run-until-settled: pauses simulation once all bodies stop moving
+
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+
+use crate::time::{PhysicsSchedule, PhysicsTime, PhysicsTimeExt, RunUntilTimeout, RunUntilTimeoutEvent};
+
+pub struct SettlePlugin;
+
+impl Plugin for SettlePlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .register_type::<SettleDetector>()
+            .init_resource::<SettleDetector>()
+            .add_systems(PhysicsSchedule, detect_settled.after(PhysicsSet::Writeback));
+    }
+}
+
+#[derive(Reflect, Resource)]
+#[reflect(Resource)]
+pub struct SettleDetector {
+    pub active: bool,
+    // max linear and angular speed of any body to be considered at rest
+    pub threshold: f32,
+    // bodies must stay below threshold for this many ticks in a row,
+    // so ball slowing down at the top of a bounce doesn't count
+    pub required_ticks: u32,
+    consecutive_ticks: u32,
+    elapsed_ticks: u32,
+}
+
+impl SettleDetector {
+    pub fn start(&mut self) {
+        self.active = true;
+        self.consecutive_ticks = 0;
+        self.elapsed_ticks = 0;
+    }
+}
+
+impl Default for SettleDetector {
+    fn default() -> Self {
+        Self {
+            active: false,
+            threshold: 0.05,
+            required_ticks: 30,
+            consecutive_ticks: 0,
+            elapsed_ticks: 0,
+        }
+    }
+}
+
+fn detect_settled(
+    mut detector: ResMut<SettleDetector>,
+    mut time: ResMut<PhysicsTime>,
+    bodies: Query<&Velocity>,
+    timeout: Res<RunUntilTimeout>,
+    mut timeout_events: EventWriter<RunUntilTimeoutEvent>,
+) {
+    if !detector.active { return; }
+
+    detector.elapsed_ticks += 1;
+    if detector.elapsed_ticks >= timeout.max_ticks {
+        detector.active = false;
+        time.pause();
+        timeout_events.send(RunUntilTimeoutEvent { ticks: detector.elapsed_ticks });
+        return;
+    }
+
+    let max_speed = bodies.iter()
+        .map(|velocity| velocity.linvel.length().max(velocity.angvel.length()))
+        .fold(0., f32::max);
+
+    if max_speed < detector.threshold {
+        detector.consecutive_ticks += 1;
+    } else {
+        detector.consecutive_ticks = 0;
+    }
+
+    if detector.consecutive_ticks >= detector.required_ticks {
+        detector.active = false;
+        time.pause();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn brief_dip_below_threshold_does_not_settle() {
+        let mut app = App::new();
+        app
+            .init_resource::<PhysicsTime>()
+            .init_resource::<RunUntilTimeout>()
+            .init_resource::<SettleDetector>()
+            .add_event::<RunUntilTimeoutEvent>()
+            .add_systems(Update, detect_settled);
+        let body = app.world.spawn(Velocity::zero()).id();
+        app.world.resource_mut::<SettleDetector>().start();
+        let required_ticks = app.world.resource::<SettleDetector>().required_ticks;
+
+        let tick = |app: &mut App, speed: f32| {
+            *app.world.get_mut::<Velocity>(body).unwrap() = Velocity::linear(Vec3::X * speed);
+            app.update();
+        };
+
+        // slows down at the top of a bounce, then falls again
+        for _ in 0..required_ticks - 1 {
+            tick(&mut app, 0.01);
+        }
+        tick(&mut app, 1.);
+        assert!(!app.world.resource::<PhysicsTime>().is_paused());
+
+        for _ in 0..required_ticks - 1 {
+            tick(&mut app, 0.01);
+        }
+        assert!(!app.world.resource::<PhysicsTime>().is_paused());
+        tick(&mut app, 0.01);
+        assert!(app.world.resource::<PhysicsTime>().is_paused());
+        assert!(!app.world.resource::<SettleDetector>().active);
+    }
+}
@@ -0,0 +1,88 @@
+// summary of the last run, computed when simulation pauses
+
+use std::time::Duration;
+
+use bevy::diagnostic::DiagnosticsStore;
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+
+use crate::time::{run_physics_schedule, start_restart_seek, DroppedTime, PhysicsSchedule, PhysicsTime, PhysicsTimeExt, PHYSICS_FPS};
+
+pub struct StatsPlugin;
+
+impl Plugin for StatsPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<RunStatistics>()
+            .add_systems(PhysicsSchedule, count_run_ticks.after(PhysicsSet::StepSimulation))
+            // mode changes from the previous frame are seen before any tick of this one,
+            // so ticks are counted towards the run they belong to
+            .add_systems(PreUpdate, update_run_statistics.after(start_restart_seek).before(run_physics_schedule));
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RunSummary {
+    pub ticks: u64,
+    pub sim_time: Duration,
+    pub average_fps: f64,
+    pub peak_fps: f64,
+    pub peak_contacts: usize,
+    pub dropped_time: Duration,
+}
+
+#[derive(Resource, Default)]
+pub struct RunStatistics {
+    // summary of last finished run, if any
+    pub summary: Option<RunSummary>,
+    current: RunSummary,
+    fps_samples: u32,
+    start_elapsed: Duration,
+    start_dropped: Duration,
+    running: bool,
+}
+
+fn count_run_ticks(mut stats: ResMut<RunStatistics>, context: Res<RapierContext>) {
+    let contacts = context.contact_pairs()
+        .filter(|pair| pair.has_any_active_contacts())
+        .count();
+
+    stats.current.ticks += 1;
+    stats.current.peak_contacts = stats.current.peak_contacts.max(contacts);
+}
+
+pub fn update_run_statistics(
+    mut stats: ResMut<RunStatistics>,
+    time: Res<PhysicsTime>,
+    dropped: Res<DroppedTime>,
+    diagnostics: Res<DiagnosticsStore>,
+) {
+    let running = !time.is_paused();
+
+    if running && !stats.running {
+        stats.current = RunSummary::default();
+        stats.fps_samples = 0;
+        stats.start_elapsed = time.elapsed();
+        stats.start_dropped = dropped.total;
+    }
+
+    if running {
+        if let Some(fps) = diagnostics.get(PHYSICS_FPS).and_then(|diagnostic| diagnostic.value()) {
+            stats.current.average_fps += fps;
+            stats.current.peak_fps = stats.current.peak_fps.max(fps);
+            stats.fps_samples += 1;
+        }
+    }
+
+    if !running && stats.running {
+        let mut summary = stats.current;
+        summary.sim_time = time.elapsed().saturating_sub(stats.start_elapsed);
+        summary.dropped_time = dropped.total.saturating_sub(stats.start_dropped);
+        if stats.fps_samples > 0 {
+            summary.average_fps /= stats.fps_samples as f64;
+        }
+        stats.summary = Some(summary);
+    }
+
+    stats.running = running;
+}
@@ -1,111 +1,1754 @@
+use std::collections::VecDeque;
 use std::time::Duration;
 
 use bevy::diagnostic::{DiagnosticId, RegisterDiagnostic, Diagnostic, Diagnostics};
-use bevy::ecs::schedule::ScheduleLabel;
+use bevy::ecs::event::ManualEventReader;
+use bevy::ecs::schedule::{ExecutorKind, ScheduleLabel};
 use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+use bevy_rapier3d::rapier::math::{Isometry, Vector};
+use bevy_rapier3d::rapier::na::{Quaternion, UnitQuaternion};
+use bevy_rapier3d::rapier::prelude::RigidBodyHandle;
 
 pub const PHYSICS_FPS: DiagnosticId = DiagnosticId::from_u128(168810318229280110473455791631253127370);
+pub const SIM_DRIFT: DiagnosticId = DiagnosticId::from_u128(291652412384215393926418047735520711683);
+// physics time advanced per second of real time
+pub const SIM_SPEED: DiagnosticId = DiagnosticId::from_u128(203391528770438211936476042880693147561);
+// seconds of accumulated time discarded by `limit_overstep` per second of real time
+pub const DROPPED_TIME: DiagnosticId = DiagnosticId::from_u128(97424168255095370342153861823041650194);
 
 pub const DEFAULT_TIMESTEP: Duration = Duration::from_micros(15625);
-pub const MAX_PHYSICS_EXEC_TIME: Duration = Duration::from_micros(15625);
+pub const DEFAULT_PHYSICS_EXEC_BUDGET: Duration = Duration::from_micros(15625);
+pub const SPEED_EPSILON: f32 = 1e-3;
+// per-frame budget while seeking, long enough to finish quickly, short enough to keep UI alive
+pub const SEEK_EXEC_BUDGET: Duration = Duration::from_millis(200);
 
 #[derive(Resource, Default)]
 pub struct DiagnosticFrameCount(u32);
 
-pub struct TimePlugin;
+// simulation time thrown away because physics couldn't keep up
+#[derive(Resource, Default)]
+pub struct DroppedTime {
+    pub total: Duration,
+    // frames in which ticks were cut short by `PhysicsExecBudget`
+    pub budget_exceeded_frames: u64,
+}
+
+// what to do when accumulated time is discarded by `limit_overstep`,
+// total is always available in `DroppedTime`
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq, Reflect)]
+#[reflect(Resource)]
+pub enum OverstepClampReaction {
+    Silent,
+    // log at most once per second, with time discarded since previous warning
+    Warn,
+    // send `OverstepClampedEvent` every frame time is discarded
+    Event,
+}
+
+impl Default for OverstepClampReaction {
+    fn default() -> Self {
+        Self::Silent
+    }
+}
+
+#[derive(Event, Debug, Clone, Copy)]
+pub struct OverstepClampedEvent {
+    pub discarded: Duration,
+}
+
+#[derive(Resource, Default)]
+struct OverstepWarning {
+    // real time of last warning
+    last: Option<Duration>,
+    discarded: Duration,
+}
+
+fn react_to_clamped_overstep(world: &mut World, discarded: Duration) {
+    if discarded.is_zero() { return; }
+    match *world.resource::<OverstepClampReaction>() {
+        OverstepClampReaction::Silent => (),
+        OverstepClampReaction::Event => {
+            world.send_event(OverstepClampedEvent { discarded });
+        }
+        OverstepClampReaction::Warn => {
+            let now = world.resource::<Time<Real>>().elapsed();
+            let mut warning = world.resource_mut::<OverstepWarning>();
+            warning.discarded += discarded;
+            if warning.last.is_some_and(|last| now.saturating_sub(last) < Duration::from_secs(1)) { return; }
+            warn!("physics can't keep up, discarded {:?} of simulation time", warning.discarded);
+            warning.last = Some(now);
+            warning.discarded = Duration::ZERO;
+        }
+    }
+}
+
+// Eases accumulated time from zero to full speed over the first frames after launch,
+// so long first frames don't queue a burst of catch-up ticks. Zero frames disables it.
+#[derive(Resource, Debug, Clone, Copy, Default, Reflect)]
+#[reflect(Resource)]
+pub struct StartupRamp {
+    pub frames: u32,
+    #[reflect(ignore)]
+    elapsed_frames: u32,
+}
+
+impl StartupRamp {
+    fn next_factor(&mut self) -> f32 {
+        if self.elapsed_frames >= self.frames { return 1.; }
+        self.elapsed_frames += 1;
+        self.elapsed_frames as f32 / self.frames as f32
+    }
+}
+
+// wall-clock time spent in the stepping loop during current frame
+#[derive(Resource, Debug, Clone, Copy, Default)]
+pub struct PhysicsFrameCost {
+    pub duration: Duration,
+    pub ticks: u32,
+}
+
+impl PhysicsFrameCost {
+    pub fn per_tick(&self) -> Option<Duration> {
+        (self.ticks > 0).then(|| self.duration / self.ticks)
+    }
+}
+
+// Runs rapier inside `PhysicsSchedule`, so rapier plugin should be added
+// with `with_default_system_setup(false)`.
+pub struct TimePlugin {
+    // PHYSICS_FPS diagnostic, required by UiPlugin
+    pub diagnostics: bool,
+    // number of samples averaged by the diagnostics, longer is steadier in fast-forward
+    pub diagnostic_history_len: usize,
+    // run PhysicsSchedule systems one by one, to find out if nondeterminism
+    // comes from parallel execution of user systems
+    pub single_threaded: bool,
+    // warn if physics time didn't advance by exactly one timestep per tick during a frame,
+    // e.g. when stepping loop is customized, enabled in debug builds by default
+    pub check_advance: bool,
+}
+
+impl TimePlugin {
+    // only stepping, schedule and rapier systems, for embedding into other apps
+    pub fn minimal() -> Self {
+        Self { diagnostics: false, ..default() }
+    }
+}
+
+impl Default for TimePlugin {
+    fn default() -> Self {
+        Self {
+            diagnostics: true,
+            diagnostic_history_len: 10,
+            single_threaded: false,
+            check_advance: cfg!(debug_assertions),
+        }
+    }
+}
+
+impl Plugin for TimePlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_schedule(PhysicsSchedule)
+            .init_schedule(WritebackSchedule)
+            .init_schedule(RestartSchedule)
+            .add_event::<SimRestart>()
+            .init_resource::<RestartRequest>()
+            .register_type::<PhysicsTime>()
+            .init_resource::<PhysicsTime>()
+            .register_type::<CatchupStrategy>()
+            .init_resource::<CatchupStrategy>()
+            .register_type::<MaxTicksPerFrame>()
+            .init_resource::<MaxTicksPerFrame>()
+            .register_type::<RunUntilTimeout>()
+            .init_resource::<RunUntilTimeout>()
+            .add_event::<RunUntilTimeoutEvent>()
+            .register_type::<AccumulationClock>()
+            .init_resource::<AccumulationClock>()
+            .init_resource::<TimestepSchedule>()
+            .init_resource::<DroppedTime>()
+            .init_resource::<PhysicsFrameCost>()
+            .register_type::<StartupRamp>()
+            .init_resource::<StartupRamp>()
+            .register_type::<OverstepClampReaction>()
+            .init_resource::<OverstepClampReaction>()
+            .init_resource::<OverstepWarning>()
+            .add_event::<OverstepClampedEvent>()
+            .register_type::<PhysicsClockState>()
+            .init_resource::<PhysicsClockState>()
+            .init_resource::<TimeTriggers>()
+            .register_type::<RewindBuffer>()
+            .init_resource::<RewindBuffer>()
+            .init_resource::<SolverOverrides>()
+            .register_type::<LoopRegion>()
+            .init_resource::<LoopRegion>()
+            .init_resource::<LoopSnapshot>()
+            .add_event::<TimeTriggerEvent>()
+            .register_type::<PhysicsExecBudget>()
+            .init_resource::<PhysicsExecBudget>()
+            .register_type::<FastForwardThrottle>()
+            .init_resource::<FastForwardThrottle>()
+            .register_type::<SimDisabled>()
+            .register_type::<SpawnedBySim>()
+            .register_type::<PhysicsInterpolate>()
+            .add_systems(PostUpdate, interpolate_transforms.after(bevy::transform::TransformSystem::TransformPropagate))
+            .add_systems(PreUpdate, (
+                disable_bodies,
+                restore_bodies,
+            ).chain().before(run_physics_schedule))
+            .add_systems(PhysicsSchedule, (
+                // before every tick, so timestep changes in the middle of a frame apply immediately
+                sync_rapier_timestep.before(PhysicsSet::StepSimulation),
+                fire_time_triggers.after(PhysicsSet::Writeback),
+                record_interpolation_poses.after(PhysicsSet::Writeback),
+                record_rewind_state.after(PhysicsSet::StepSimulation),
+                RapierPhysicsPlugin::<NoUserData>::get_systems(PhysicsSet::SyncBackend)
+                    .in_set(PhysicsSet::SyncBackend),
+                RapierPhysicsPlugin::<NoUserData>::get_systems(PhysicsSet::StepSimulation)
+                    .in_set(PhysicsSet::StepSimulation),
+                RapierPhysicsPlugin::<NoUserData>::get_systems(PhysicsSet::Writeback)
+                    .in_set(PhysicsSet::Writeback),
+            ))
+            .add_systems(WritebackSchedule,
+                RapierPhysicsPlugin::<NoUserData>::get_systems(PhysicsSet::Writeback)
+                    .in_set(PhysicsSet::Writeback),
+            )
+            .add_event::<PhysicsModeChanged>()
+            .add_event::<PhysicsBreakpointHit>()
+            .add_event::<PhysicsTick>()
+            .add_systems(Last, (
+                bevy_rapier3d::plugin::systems::sync_removals,
+                detect_mode_change,
+            ))
+            .add_systems(PreUpdate, (
+                restart_for_seek.before(restart_simulation),
+                restart_simulation.before(run_physics_schedule),
+                start_restart_seek.after(restart_simulation).before(run_physics_schedule),
+                apply_solver_overrides.before(run_physics_schedule),
+                apply_step_back.before(run_physics_schedule),
+                run_physics_schedule,
+                update_clock_state.after(run_physics_schedule),
+            ));
+
+        if self.single_threaded {
+            app.edit_schedule(PhysicsSchedule, |schedule| {
+                schedule.set_executor_kind(ExecutorKind::SingleThreaded);
+            });
+        }
+
+        if self.check_advance {
+            app.add_systems(PreUpdate, check_time_advance.after(run_physics_schedule));
+        }
+
+        if self.diagnostics {
+            let history_len = self.diagnostic_history_len;
+            app
+                .init_resource::<DiagnosticFrameCount>()
+                .register_diagnostic(Diagnostic::new(PHYSICS_FPS, "physics_fps", history_len))
+                .register_diagnostic(Diagnostic::new(SIM_DRIFT, "sim_drift", history_len).with_suffix("s"))
+                .register_diagnostic(Diagnostic::new(SIM_SPEED, "sim_speed", history_len).with_suffix("x"))
+                .register_diagnostic(Diagnostic::new(DROPPED_TIME, "dropped_time", history_len).with_suffix("s/s"))
+                .add_systems(PhysicsSchedule, diagnosics_count)
+                .add_systems(Update, diagnostics_report);
+        }
+    }
+}
+
+// Pauses physics while app is in any of given states and resumes it in others,
+// with the speed it had before (see `PhysicsTimeExt::resume`). Physics paused by
+// the user before entering such state stays paused after leaving it.
+pub struct PauseInStatesPlugin<S: States> {
+    pub states: Vec<S>,
+}
+
+impl<S: States> PauseInStatesPlugin<S> {
+    pub fn new(states: impl IntoIterator<Item = S>) -> Self {
+        Self { states: states.into_iter().collect() }
+    }
+}
+
+impl<S: States> Plugin for PauseInStatesPlugin<S> {
+    fn build(&self, app: &mut App) {
+        app
+            .insert_resource(PausedStates(self.states.clone()))
+            .add_systems(PreUpdate, pause_in_states::<S>.before(run_physics_schedule));
+    }
+}
+
+#[derive(Resource, Debug, Clone)]
+pub struct PausedStates<S: States>(pub Vec<S>);
+
+fn pause_in_states<S: States>(
+    paused_states: Res<PausedStates<S>>,
+    state: Option<Res<State<S>>>,
+    mut time: ResMut<PhysicsTime>,
+    mut paused_by_state: Local<bool>,
+) {
+    let Some(state) = state else { return; };
+    if !state.is_changed() && !paused_states.is_changed() { return; }
+
+    let should_pause = paused_states.0.contains(state.get());
+    let paused = time.context().mode == PhysicsTimeMode::Paused;
+    if should_pause && !paused {
+        time.pause();
+        *paused_by_state = true;
+    } else if !should_pause && *paused_by_state {
+        if paused { time.resume(); }
+        *paused_by_state = false;
+    }
+}
+
+#[derive(ScheduleLabel, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct PhysicsSchedule;
+
+// Sent right before every tick, so `EventReader<PhysicsTick>` in `PhysicsSchedule` sees exactly
+// one per run. Readers in other schedules get all ticks of the frame, including ticks run by
+// `force_single_step` (e.g. startup pre-roll), which are marked as `forced`.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct PhysicsTick {
+    // 1 for the first tick after restart, same as `elapsed_ticks()` during the tick
+    pub tick: u64,
+    pub timestep: Duration,
+    // run by `force_single_step` outside of the frame loop, mode and accumulated time are ignored
+    pub forced: bool,
+}
+
+fn send_tick_event(world: &mut World, forced: bool) {
+    let time = world.resource::<PhysicsTime>();
+    let event = PhysicsTick {
+        tick: time.elapsed_ticks(),
+        timestep: time.context().timestep,
+        forced,
+    };
+    world.send_event(event);
+}
+
+// only rapier writeback systems, see `force_writeback`
+#[derive(ScheduleLabel, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct WritebackSchedule;
+
+// Restarts simulation on the next frame: clock starts from zero (keeping mode, speed and
+// timestep, see `PhysicsTimeInner::restarted`), then `RestartSchedule` is run.
+#[derive(Event, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SimRestart {
+    // seed for the app to respawn the scene with, read it from `RestartRequest`
+    pub seed: Option<u64>,
+    // fast-forward to this tick after restart, like `PhysicsTimeExt::run_to_tick`
+    pub target_tick: Option<u64>,
+}
+
+impl SimRestart {
+    // restart with given seed and fast-forward to given tick, e.g. to reproduce a bug seen at that tick
+    pub fn seek(seed: u64, target_tick: u64) -> Self {
+        Self { seed: Some(seed), target_tick: Some(target_tick) }
+    }
+}
+
+// Restart being handled, so `RestartSchedule` systems can use the requested seed.
+// Target tick is taken when the seek starts.
+#[derive(Resource, Debug, Clone, Copy, Default)]
+pub struct RestartRequest(pub SimRestart);
+
+// App-specific part of a restart, e.g. respawning the scene. Runs before physics in the frame
+// after `SimRestart` is sent, with commands applied at the end.
+#[derive(ScheduleLabel, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct RestartSchedule;
+
+// Entities (with their children) despawned on every restart before `RestartSchedule` runs,
+// so anything spawned there is cleaned up without app-specific code.
+#[derive(Component, Debug, Default, Clone, Copy, Reflect)]
+#[reflect(Component)]
+pub struct SpawnedBySim;
+
+pub fn restart_simulation(world: &mut World, mut reader: Local<ManualEventReader<SimRestart>>) {
+    // several restarts in one frame are the same as one, later requests take precedence
+    let restart = reader.read(world.resource::<Events<SimRestart>>())
+        .copied()
+        .reduce(|merged, restart| SimRestart {
+            seed: restart.seed.or(merged.seed),
+            target_tick: restart.target_tick.or(merged.target_tick),
+        });
+    let Some(restart) = restart else { return; };
+    world.insert_resource(RestartRequest(restart));
+
+    let spawned: Vec<Entity> = world.query_filtered::<Entity, With<SpawnedBySim>>().iter(world).collect();
+    for entity in spawned {
+        // may be gone already as a child of another one
+        let Some(entity) = world.get_entity_mut(entity) else { continue; };
+        entity.despawn_recursive();
+    }
+
+    let mut time = world.resource_mut::<PhysicsTime>();
+    *time = PhysicsTime::new_with(time.context().restarted());
+    apply_timestep_schedule(world);
+    world.run_schedule(RestartSchedule);
+}
+
+// seeking backwards re-simulates from the start
+pub fn restart_for_seek(time: Res<PhysicsTime>, mut events: EventWriter<SimRestart>) {
+    if !time.context().seek_restart_pending() { return; }
+    events.send(SimRestart::default());
+}
+
+// Separate from `restart_simulation`, so anything that runs ticks right after a restart
+// (e.g. pre-roll) can be ordered before it and counts towards the target.
+pub fn start_restart_seek(mut request: ResMut<RestartRequest>, mut time: ResMut<PhysicsTime>) {
+    let Some(target_tick) = request.0.target_tick.take() else { return; };
+    time.run_to_tick(target_tick);
+}
+
+// copies current rapier state into transforms and velocities without advancing time,
+// e.g. after rapier bodies were modified directly while paused
+pub fn force_writeback(world: &mut World) {
+    world.run_schedule(WritebackSchedule);
+}
+
+// Runs one tick immediately, regardless of mode and accumulated time, unlike `step()` which
+// waits for the next frame. Mode and overstep are untouched. Can't be called from
+// `PhysicsSchedule` itself.
+pub fn force_single_step(world: &mut World) {
+    let mut time = world.resource_mut::<PhysicsTime>();
+    let timestep = time.context().timestep;
+    time.context_mut().ticks += 1;
+    time.advance_by(timestep);
+    let elapsed = time.elapsed();
+    time.context_mut().reach(elapsed);
+    send_tick_event(world, true);
+    world.run_schedule(PhysicsSchedule);
+    apply_timestep_schedule(world);
+    apply_loop_region(world);
+}
+
+pub type PhysicsTime = Time<PhysicsTimeInner>;
+
+pub trait PhysicsTimeExt {
+    fn pause(&mut self);
+    fn resume(&mut self);
+    fn toggle_pause(&mut self);
+    fn is_paused(&self) -> bool;
+    fn is_running(&self) -> bool;
+    fn speed(&self) -> Option<f32>;
+    fn step(&mut self);
+    fn step_by(&mut self, ticks: u32);
+    fn run(&mut self, speed: f32);
+    fn run_eased(&mut self, speed: f32, ramp: Duration);
+    fn boost(&mut self, speed: f32, ticks: u32);
+    fn run_to_tick(&mut self, target_tick: u64);
+    fn run_for(&mut self, duration: Duration);
+    fn run_until(&mut self, target: Duration);
+    fn seek(&mut self, target: Duration);
+    fn auto_step(&mut self, interval: Duration);
+    fn fixed_ticks_per_frame(&mut self, ticks: u32);
+    fn set_timestep(&mut self, timestep: Duration);
+    fn direction(&self) -> PlaybackDirection;
+    fn elapsed_secs_f64(&self) -> f64;
+    fn elapsed_ticks(&self) -> u64;
+    fn tick_count(&self) -> u64;
+    fn overstep(&self) -> Duration;
+    fn timestep(&self) -> Duration;
+    fn interpolation_alpha(&self) -> f32;
+    fn step_pending(&self) -> bool;
+    fn bounded_progress(&self) -> Option<(u32, u32)>;
+    fn furthest_reached(&self) -> Duration;
+    fn step_back(&mut self);
+    fn measured_speed(&self) -> f32;
+    fn save_state(&self) -> PhysicsTimeState;
+    fn load_state(&mut self, state: PhysicsTimeState);
+}
+
+impl PhysicsTimeExt for PhysicsTime {
+    fn pause(&mut self) {
+        self.context_mut().set_mode(PhysicsTimeMode::Paused);
+    }
+
+    fn resume(&mut self) {
+        let old_mode = self.context().old_mode;
+        self.context_mut().set_mode(old_mode);
+    }
+
+    // pause in any other mode, including pending steps
+    fn toggle_pause(&mut self) {
+        if self.is_paused() {
+            self.resume();
+        } else {
+            self.pause();
+        }
+    }
+
+    fn is_paused(&self) -> bool {
+        self.context().mode == PhysicsTimeMode::Paused
+    }
+
+    // running continuously with some speed, steps and auto-step don't count
+    fn is_running(&self) -> bool {
+        matches!(self.context().mode, PhysicsTimeMode::Running { .. })
+    }
+
+    // requested speed while running, infinite in fast-forward
+    fn speed(&self) -> Option<f32> {
+        match self.context().mode {
+            PhysicsTimeMode::Running { speed } => Some(speed),
+            _ => None,
+        }
+    }
+
+    // Runs exactly one tick on the next frame, then pauses, regardless of current mode:
+    //  - Paused, AutoStep: one tick, then Paused
+    //  - OneTick: still one tick, repeated calls within a frame don't add up
+    //  - Running (including boost): no time from current frame is accumulated, so there's
+    //    no extra tick; sub-tick overstep is kept and continues after `resume()`
+    // `resume()` afterwards returns to the last Running or AutoStep mode.
+    fn step(&mut self) {
+        self.context_mut().set_mode(PhysicsTimeMode::OneTick);
+    }
+
+    // Like `step()`, but runs given number of ticks as fast as possible, then pauses.
+    // Zero ticks does nothing.
+    fn step_by(&mut self, ticks: u32) {
+        if ticks == 0 { return; }
+        let context = self.context_mut();
+        context.set_mode(PhysicsTimeMode::NTicks { remaining: ticks });
+        context.bounded_total = ticks;
+    }
+
+    fn run(&mut self, speed: f32) {
+        // zero speed would never accumulate time, which is just pause that UI doesn't recognize,
+        // negative and NaN speeds can't be accumulated at all
+        if speed.is_nan() || speed <= 0. {
+            self.pause();
+            return;
+        }
+        self.context_mut().set_mode(PhysicsTimeMode::Running { speed });
+    }
+
+    // Like `run()`, but speed changes gradually over `ramp` of accumulated frame time, starting
+    // from current speed (zero if not running). Infinite speed ramps up to `ease_max_speed`
+    // and switches to fast-forward at the end, zero ramps down and pauses.
+    fn run_eased(&mut self, speed: f32, ramp: Duration) {
+        if speed.is_nan() || ramp.is_zero() {
+            self.run(speed);
+            return;
+        }
+        let context = self.context_mut();
+        let max_speed = context.ease_max_speed;
+        let from = match context.mode {
+            PhysicsTimeMode::Running { speed: current } => current.min(max_speed),
+            _ => 0.,
+        };
+        let then = if speed > 0. { PhysicsTimeMode::Running { speed } } else { PhysicsTimeMode::Paused };
+        context.set_mode(then);
+        // stays paused until ramped speed is above zero
+        context.mode = if from > 0. { PhysicsTimeMode::Running { speed: from } } else { PhysicsTimeMode::Paused };
+        context.speed_ramp = Some(SpeedRamp {
+            from,
+            to: speed.clamp(0., max_speed),
+            duration: ramp,
+            elapsed: Duration::ZERO,
+            then,
+        });
+    }
+
+    // run with given speed for a number of ticks, then return to `old_mode`
+    fn boost(&mut self, speed: f32, ticks: u32) {
+        if ticks == 0 || speed.is_nan() || speed <= 0. { return; }
+        let context = self.context_mut();
+        context.override_mode(PhysicsTimeMode::Running { speed });
+        context.boost_ticks = Some(ticks);
+        context.bounded_total = ticks;
+        context.boost_then = None;
+    }
+
+    // fast-forward up to given tick counter and pause there
+    fn run_to_tick(&mut self, target_tick: u64) {
+        let remaining = target_tick.saturating_sub(self.context().ticks);
+        if remaining == 0 { return; }
+        self.boost(f32::INFINITY, remaining.min(u32::MAX as u64) as u32);
+        let context = self.context_mut();
+        context.boost_then = Some(PhysicsTimeMode::Paused);
+        context.run_until_ticks = 0;
+    }
+
+    // keep running (or resume) until given elapsed time, then pause
+    fn run_for(&mut self, duration: Duration) {
+        let target = self.elapsed().saturating_add(duration);
+        self.run_until(target);
+    }
+
+    // Pauses on the first tick at or past `target`, so it's rounded up to a whole tick.
+    // Speed can be changed with `run()` in the meantime, pausing cancels the target.
+    fn run_until(&mut self, target: Duration) {
+        if self.elapsed() >= target {
+            self.pause();
+            return;
+        }
+        if !matches!(
+            self.context().mode,
+            PhysicsTimeMode::Running { .. } | PhysicsTimeMode::AutoStep { .. } | PhysicsTimeMode::FixedTicksPerFrame { .. },
+        ) {
+            self.resume();
+        }
+        let context = self.context_mut();
+        context.run_target = Some(target);
+        context.run_until_ticks = 0;
+    }
+
+    // Fast-forwards to given elapsed time with a larger exec budget and pauses there.
+    // Earlier time can only be reached by re-simulating from the start: the clock then waits
+    // for the app to restart (see `PhysicsTimeInner::seek_restart_pending`) and seeks after it.
+    fn seek(&mut self, target: Duration) {
+        let elapsed = self.elapsed();
+        if target == elapsed {
+            self.pause();
+            return;
+        }
+        let context = self.context_mut();
+        if target < elapsed {
+            context.set_mode(PhysicsTimeMode::Paused);
+            context.seek_after_restart = Some(target);
+            return;
+        }
+        context.start_seek(target);
+    }
+
+    fn auto_step(&mut self, interval: Duration) {
+        self.context_mut().set_mode(PhysicsTimeMode::AutoStep { interval });
+    }
+
+    // zero ticks is the same as pause
+    fn fixed_ticks_per_frame(&mut self, ticks: u32) {
+        if ticks == 0 {
+            self.pause();
+            return;
+        }
+        self.context_mut().set_mode(PhysicsTimeMode::FixedTicksPerFrame { ticks });
+    }
+
+    // Rapier timestep is kept in sync with this value before every tick. Accumulated time
+    // is clamped with the new value right away, so shrinking timestep doesn't cause a burst.
+    fn set_timestep(&mut self, timestep: Duration) {
+        if timestep.is_zero() { return; }
+        let context = self.context_mut();
+        context.timestep = timestep;
+        if context.overstep != Duration::MAX {
+            context.overstep = context.overstep.min(timestep * 3);
+        }
+    }
+
+    fn direction(&self) -> PlaybackDirection {
+        match self.context().mode {
+            PhysicsTimeMode::Paused => PlaybackDirection::Stopped,
+            PhysicsTimeMode::OneTick => PlaybackDirection::Forward,
+            PhysicsTimeMode::NTicks { .. } => PlaybackDirection::Forward,
+            PhysicsTimeMode::AutoStep { .. } => PlaybackDirection::Forward,
+            PhysicsTimeMode::FixedTicksPerFrame { .. } => PlaybackDirection::Forward,
+            PhysicsTimeMode::Running { speed } => {
+                if speed > 0. {
+                    PlaybackDirection::Forward
+                } else if speed < 0. {
+                    PlaybackDirection::Backward
+                } else {
+                    PlaybackDirection::Stopped
+                }
+            }
+        }
+    }
+
+    fn elapsed_secs_f64(&self) -> f64 {
+        self.elapsed().as_secs_f64()
+    }
+
+    // ticks executed since restart
+    fn elapsed_ticks(&self) -> u64 {
+        self.context().ticks
+    }
+
+    // same as `elapsed_ticks`
+    fn tick_count(&self) -> u64 {
+        self.context().ticks
+    }
+
+    // accumulated time not yet spent on ticks, `Duration::MAX` in fast-forward
+    fn overstep(&self) -> Duration {
+        self.context().overstep
+    }
+
+    fn timestep(&self) -> Duration {
+        self.context().timestep
+    }
+
+    // how far accumulated time got towards the next tick, 0..=1
+    fn interpolation_alpha(&self) -> f32 {
+        let context = self.context();
+        (context.overstep.as_secs_f32() / context.timestep.as_secs_f32()).clamp(0., 1.)
+    }
+
+    // Pauses and restores state from before the last tick on the next frame, see `RewindBuffer`.
+    fn step_back(&mut self) {
+        self.pause();
+        self.context_mut().step_back_requests += 1;
+    }
+
+    // a requested step hasn't been executed yet, it will on the next physics run
+    fn step_pending(&self) -> bool {
+        matches!(self.context().mode, PhysicsTimeMode::OneTick | PhysicsTimeMode::NTicks { .. })
+    }
+
+    // (remaining, total) ticks of a run that stops by itself, like `boost`, `step_by`
+    // or `run_until`, the latter estimated with current timestep
+    fn bounded_progress(&self) -> Option<(u32, u32)> {
+        let context = self.context();
+        if let PhysicsTimeMode::NTicks { remaining } = context.mode {
+            return Some((remaining, context.bounded_total));
+        }
+        if let Some(remaining) = context.boost_ticks {
+            return Some((remaining, context.bounded_total));
+        }
+        let target = context.run_target?;
+        let left = target.saturating_sub(self.elapsed()).as_nanos();
+        let remaining = left.div_ceil(context.timestep.as_nanos()).min(u32::MAX as u128) as u32;
+        Some((remaining, context.run_until_ticks.saturating_add(remaining)))
+    }
+
+    // Latest elapsed time reached since restart, e.g. the end of a seek timeline. Kept when
+    // restarting for a backward seek, and when stepping back.
+    fn furthest_reached(&self) -> Duration {
+        self.context().furthest.max(self.elapsed())
+    }
+
+    // Physics time advanced per second of real time during the last frame, always finite.
+    // Jumps between whole ticks per frame, `SIM_SPEED` diagnostic has a smoothed value.
+    fn measured_speed(&self) -> f32 {
+        self.context().measured_speed
+    }
+
+    fn save_state(&self) -> PhysicsTimeState {
+        PhysicsTimeState {
+            inner: *self.context(),
+            elapsed: self.elapsed(),
+        }
+    }
+
+    // Clock continues exactly where it was saved, pending step-back requests are dropped.
+    // Body state isn't part of it, restore rapier state alongside.
+    fn load_state(&mut self, state: PhysicsTimeState) {
+        *self = PhysicsTime::new_with(state.inner);
+        self.advance_by(state.elapsed);
+    }
+}
+
+// Everything needed to restore the physics clock, see `PhysicsTimeExt::save_state`.
+// With `serialize` feature it can be written to disk, e.g. next to a rapier snapshot.
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+pub struct PhysicsTimeState {
+    pub inner: PhysicsTimeInner,
+    pub elapsed: Duration,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PlaybackDirection {
+    Forward,
+    Backward,
+    Stopped,
+}
+
+#[derive(Debug, Copy, Clone, Reflect)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+#[reflect(Default)]
+pub struct PhysicsTimeInner {
+    pub mode: PhysicsTimeMode,
+    old_mode: PhysicsTimeMode,
+    pub timestep: Duration,
+    pub overstep: Duration,
+    // number of executed ticks
+    ticks: u64,
+    // seconds simulation fell behind real time while running at 1x
+    drift: f64,
+    // see `PhysicsTimeExt::measured_speed`
+    measured_speed: f32,
+    // time since last tick in AutoStep mode
+    #[reflect(ignore)]
+    auto_step_elapsed: Duration,
+    // ticks not yet run in current frame in FixedTicksPerFrame mode
+    #[reflect(ignore)]
+    frame_ticks_left: u32,
+    boost_ticks: Option<u32>,
+    // total ticks of current boost or `step_by`, for progress display
+    #[reflect(ignore)]
+    bounded_total: u32,
+    // mode after boost ends, `old_mode` if None
+    #[reflect(ignore)]
+    boost_then: Option<PhysicsTimeMode>,
+    #[reflect(ignore)]
+    #[cfg_attr(feature = "serialize", serde(skip))]
+    step_back_requests: u32,
+    // elapsed time to pause at, see `run_until`
+    run_target: Option<Duration>,
+    // ticks since current `run_until`, `run_to_tick` or seek started, see `RunUntilTimeout`
+    #[reflect(ignore)]
+    run_until_ticks: u32,
+    // set when a run was paused by `RunUntilTimeout`, taken by the stepping loop to send the event
+    #[reflect(ignore)]
+    #[cfg_attr(feature = "serialize", serde(skip))]
+    timed_out: Option<u32>,
+    // fast-forwarding to `run_target` with `SEEK_EXEC_BUDGET`
+    #[reflect(ignore)]
+    seeking: bool,
+    // seek target before current time, applied to the clock created on restart
+    #[reflect(ignore)]
+    seek_after_restart: Option<Duration>,
+    // see `PhysicsTimeExt::furthest_reached`
+    #[reflect(ignore)]
+    furthest: Duration,
+    #[reflect(ignore)]
+    speed_ramp: Option<SpeedRamp>,
+    // `run_eased` to infinite speed ramps up to this speed before switching to fast-forward
+    pub ease_max_speed: f32,
+}
+
+// speed change in progress, see `PhysicsTimeExt::run_eased`
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+struct SpeedRamp {
+    from: f32,
+    // finite, unlike the speed in `then`
+    to: f32,
+    duration: Duration,
+    elapsed: Duration,
+    // mode set when ramp is over
+    then: PhysicsTimeMode,
+}
+
+impl PhysicsTimeInner {
+    pub fn new(mode: PhysicsTimeMode, timestep: Duration) -> Self {
+        let mut result = Self { timestep, ..default() };
+        result.set_mode(mode);
+        result
+    }
+
+    pub fn drift_secs(&self) -> f64 {
+        self.drift
+    }
+
+    // fresh clock that keeps user-selected mode, speed and timestep
+    pub fn restarted(&self) -> Self {
+        // temporary boost or seek isn't carried over
+        let mode = if self.boost_ticks.is_some() || self.seeking || self.speed_ramp.is_some() {
+            self.old_mode
+        } else {
+            self.mode
+        };
+        let mut result = Self {
+            mode,
+            old_mode: self.old_mode,
+            timestep: self.timestep,
+            ease_max_speed: self.ease_max_speed,
+            // seeking back re-simulates the same run
+            furthest: if self.seek_after_restart.is_some() { self.furthest } else { Duration::ZERO },
+            ..default()
+        };
+        match self.seek_after_restart {
+            Some(target) if !target.is_zero() => result.start_seek(target),
+            Some(_) => result.set_mode(PhysicsTimeMode::Paused),
+            None => (),
+        }
+        result
+    }
+
+    fn reach(&mut self, elapsed: Duration) {
+        self.furthest = self.furthest.max(elapsed);
+    }
+
+    // backward seek waits for the simulation to be restarted
+    pub fn seek_restart_pending(&self) -> bool {
+        self.seek_after_restart.is_some()
+    }
+
+    // like `boost`, `old_mode` is kept for `resume()` after seek
+    fn start_seek(&mut self, target: Duration) {
+        self.set_mode(PhysicsTimeMode::Paused);
+        self.mode = PhysicsTimeMode::Running { speed: f32::INFINITY };
+        self.run_target = Some(target);
+        self.run_until_ticks = 0;
+        self.seeking = true;
+    }
+
+    // Sets mode without touching `old_mode`, for temporary modes the caller restores by itself
+    // (e.g. play while a key is held). Speed ramp in progress is cancelled, boost and run
+    // target are kept.
+    pub fn override_mode(&mut self, mode: PhysicsTimeMode) {
+        self.speed_ramp = None;
+        self.mode = mode;
+    }
+
+    // mode reported by `PhysicsModeChanged`, speed ramp counts as already at its target
+    fn reported_mode(&self) -> PhysicsTimeMode {
+        self.speed_ramp.map_or(self.mode, |ramp| ramp.then)
+    }
+
+    pub fn set_mode(&mut self, mode: PhysicsTimeMode) {
+        // fast-forward accumulator is unbounded, it mustn't be kept (or reported as dropped)
+        // when a run is paused in the middle of a frame, e.g. by `run_until`
+        if self.mode == (PhysicsTimeMode::Running { speed: f32::INFINITY }) && mode != self.mode {
+            self.overstep = Duration::ZERO;
+        }
+        self.boost_ticks = None;
+        self.boost_then = None;
+        self.seeking = false;
+        self.seek_after_restart = None;
+        self.speed_ramp = None;
+        // stepping pauses by itself, a target left over would stop a later `resume()`
+        if let PhysicsTimeMode::Paused | PhysicsTimeMode::OneTick | PhysicsTimeMode::NTicks { .. } = mode {
+            self.run_target = None;
+        }
+        if let PhysicsTimeMode::Running { .. }
+            | PhysicsTimeMode::AutoStep { .. }
+            | PhysicsTimeMode::FixedTicksPerFrame { .. } = mode
+        {
+            self.old_mode = mode;
+        }
+        self.mode = mode;
+    }
+}
+
+impl Default for PhysicsTimeInner {
+    fn default() -> Self {
+        Self {
+            mode:     PhysicsTimeMode::default(),
+            old_mode: PhysicsTimeMode::default(),
+            timestep: DEFAULT_TIMESTEP,
+            overstep: Duration::ZERO,
+            ticks: 0,
+            drift: 0.,
+            measured_speed: 0.,
+            auto_step_elapsed: Duration::ZERO,
+            frame_ticks_left: 0,
+            boost_ticks: None,
+            bounded_total: 0,
+            boost_then: None,
+            step_back_requests: 0,
+            run_target: None,
+            run_until_ticks: 0,
+            timed_out: None,
+            seeking: false,
+            seek_after_restart: None,
+            furthest: Duration::ZERO,
+            speed_ramp: None,
+            ease_max_speed: 64.,
+        }
+    }
+}
+
+// Sent at the end of a frame in which mode changed, so a mode that was set and replaced within
+// one frame isn't reported. One-tick steps are seen as two changes: to OneTick and back.
+// `run_eased` is reported once, with the speed it ramps to.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct PhysicsModeChanged {
+    pub old: PhysicsTimeMode,
+    pub new: PhysicsTimeMode,
+}
+
+fn detect_mode_change(
+    time: Res<PhysicsTime>,
+    mut events: EventWriter<PhysicsModeChanged>,
+    mut last_mode: Local<Option<PhysicsTimeMode>>,
+) {
+    let new = time.context().reported_mode();
+    let Some(old) = last_mode.replace(new) else { return; };
+    if old == new { return; }
+    events.send(PhysicsModeChanged { old, new });
+}
+
+// Copy of the physics clock for the world inspector, updated every frame after physics runs.
+// Editing it has no effect, change `PhysicsTime` instead.
+#[derive(Resource, Debug, Clone, Copy, Default, PartialEq, Reflect)]
+#[reflect(Resource)]
+pub struct PhysicsClockState {
+    pub mode: PhysicsTimeMode,
+    // mode restored by `resume()`
+    pub old_mode: PhysicsTimeMode,
+    pub timestep: Duration,
+    // accumulated time not yet spent on ticks
+    pub overstep: Duration,
+    pub elapsed: Duration,
+    pub ticks: u64,
+    pub drift_secs: f64,
+    pub boost_ticks_left: Option<u32>,
+}
+
+fn update_clock_state(time: Res<PhysicsTime>, mut state: ResMut<PhysicsClockState>) {
+    let context = time.context();
+    state.set_if_neq(PhysicsClockState {
+        mode: context.mode,
+        old_mode: context.old_mode,
+        timestep: context.timestep,
+        overstep: context.overstep,
+        elapsed: time.elapsed(),
+        ticks: context.ticks,
+        drift_secs: context.drift,
+        boost_ticks_left: context.boost_ticks,
+    });
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Reflect)]
+pub enum PhysicsTimeMode {
+    Paused,
+    OneTick,
+    // runs this many more ticks without waiting for accumulated time, then pauses
+    NTicks { remaining: u32 },
+    Running { speed: f32 },
+    // one tick per interval of real time, like a slideshow
+    AutoStep { interval: Duration },
+    // Exactly this many ticks every frame regardless of frame time and exec budget, so the
+    // result doesn't depend on frame rate, e.g. for headless tests.
+    FixedTicksPerFrame { ticks: u32 },
+}
+
+impl PhysicsTimeMode {
+    // like `==`, but speeds within epsilon (relative to larger speed) are considered equal
+    pub fn approx_eq(&self, other: &Self, epsilon: f32) -> bool {
+        match (self, other) {
+            (Self::Running { speed: a }, Self::Running { speed: b }) => {
+                a == b || (a.is_finite() && b.is_finite() && (a - b).abs() <= epsilon * a.abs().max(b.abs()))
+            }
+            _ => self == other,
+        }
+    }
+}
+
+impl Default for PhysicsTimeMode {
+    fn default() -> Self {
+        Self::Running { speed: 1. }
+    }
+}
+
+// Stable textual form for logs and config: "paused", "one_tick", "n_ticks:10", "running:1.0", "running:inf",
+// "auto_step:0.5" (interval in seconds), "fixed_ticks:4". Parsing is case-insensitive and accepts "running"
+// without speed as 1x.
+impl std::fmt::Display for PhysicsTimeMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Paused => write!(f, "paused"),
+            Self::OneTick => write!(f, "one_tick"),
+            Self::NTicks { remaining } => write!(f, "n_ticks:{}", remaining),
+            // Debug keeps the decimal point and prints "inf"
+            Self::Running { speed } => write!(f, "running:{:?}", speed),
+            Self::AutoStep { interval } => write!(f, "auto_step:{:?}", interval.as_secs_f64()),
+            Self::FixedTicksPerFrame { ticks } => write!(f, "fixed_ticks:{}", ticks),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseModeError(String);
+
+impl std::fmt::Display for ParseModeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "invalid physics time mode: {:?}", self.0)
+    }
+}
+
+impl std::error::Error for ParseModeError {}
+
+// Stored as its string form, so infinite speed survives formats without infinity (e.g. JSON).
+#[cfg(feature = "serialize")]
+impl serde::Serialize for PhysicsTimeMode {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(feature = "serialize")]
+impl<'de> serde::Deserialize<'de> for PhysicsTimeMode {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = String::deserialize(deserializer)?;
+        value.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+impl std::str::FromStr for PhysicsTimeMode {
+    type Err = ParseModeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let error = || ParseModeError(s.to_owned());
+        let lower = s.trim().to_ascii_lowercase();
+        let (name, arg) = match lower.split_once(':') {
+            Some((name, arg)) => (name, Some(arg.trim())),
+            None => (lower.as_str(), None),
+        };
+
+        match (name, arg) {
+            ("paused", None) => Ok(Self::Paused),
+            ("one_tick", None) => Ok(Self::OneTick),
+            ("n_ticks", Some(remaining)) => {
+                let remaining = remaining.parse().map_err(|_| error())?;
+                if remaining == 0 { return Err(error()); }
+                Ok(Self::NTicks { remaining })
+            }
+            ("running", None) => Ok(Self::Running { speed: 1. }),
+            ("running", Some(speed)) => {
+                let speed: f32 = speed.parse().map_err(|_| error())?;
+                if speed.is_nan() || speed <= 0. { return Err(error()); }
+                Ok(Self::Running { speed })
+            }
+            ("auto_step", Some(interval)) => {
+                let interval: f64 = interval.parse().map_err(|_| error())?;
+                let interval = Duration::try_from_secs_f64(interval).map_err(|_| error())?;
+                Ok(Self::AutoStep { interval })
+            }
+            ("fixed_ticks", Some(ticks)) => {
+                let ticks = ticks.parse().map_err(|_| error())?;
+                if ticks == 0 { return Err(error()); }
+                Ok(Self::FixedTicksPerFrame { ticks })
+            }
+            _ => Err(error()),
+        }
+    }
+}
+
+// Limit for modes that run until some condition is met, in case it never is: `run_until`,
+// `run_for`, `run_to_tick`, seek and the settle detector. Counted in ticks since the run started.
+#[derive(Resource, Debug, Clone, Copy, Reflect)]
+#[reflect(Resource)]
+pub struct RunUntilTimeout {
+    pub max_ticks: u32,
+}
+
+impl Default for RunUntilTimeout {
+    fn default() -> Self {
+        // enough to seek through ~25 minutes at default timestep
+        Self { max_ticks: 100_000 }
+    }
+}
+
+// sent when a run-until mode was paused by `RunUntilTimeout`
+#[derive(Event, Debug, Clone, Copy)]
+pub struct RunUntilTimeoutEvent {
+    pub ticks: u32,
+}
+
+// Clock that feeds the physics accumulator.
+//
+// Physics runs in PreUpdate and egui in Update of the same frame, so UI interaction
+// can't starve the stepping loop directly, it only makes frames longer. With long frames,
+// `Time<Virtual>` clamps delta to its `max_delta` (250ms by default), and that time is lost,
+// which is visible as a hitch. `Real` accumulates unclamped wall-clock time instead, leftover
+// is still limited by `limit_overstep` and shows up in `DroppedTime`.
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq, Reflect)]
+#[reflect(Resource)]
+pub enum AccumulationClock {
+    Virtual,
+    Real,
+}
+
+impl Default for AccumulationClock {
+    fn default() -> Self {
+        Self::Virtual
+    }
+}
+
+// Body with this component is rendered between its last two tick poses, by how far
+// accumulated time got towards the next tick, so it moves smoothly when there are fewer ticks
+// than frames. It's one tick behind the simulation in exchange. Only `GlobalTransform` is
+// interpolated (bypassing change detection, so rapier doesn't pick it up), `Transform` keeps
+// the physics pose. Without it, bodies are rendered at the latest tick pose.
+// Blending runs in PostUpdate after transform propagation rather than in Update, since
+// propagation would overwrite the interpolated `GlobalTransform` from `Transform` otherwise.
+#[derive(Component, Debug, Default, Clone, Copy, Reflect)]
+#[reflect(Component)]
+pub struct PhysicsInterpolate {
+    previous: Option<Transform>,
+    current: Option<Transform>,
+}
+
+fn record_interpolation_poses(mut bodies: Query<(&Transform, &mut PhysicsInterpolate)>) {
+    for (transform, mut interpolate) in bodies.iter_mut() {
+        interpolate.previous = Some(interpolate.current.unwrap_or(*transform));
+        interpolate.current = Some(*transform);
+    }
+}
+
+fn interpolate_transforms(
+    time: Res<PhysicsTime>,
+    mut bodies: Query<(&Transform, &mut GlobalTransform, &mut PhysicsInterpolate, Option<&Parent>)>,
+    parents: Query<&GlobalTransform, Without<PhysicsInterpolate>>,
+) {
+    let context = time.context();
+    // no steady accumulation to interpolate by when paused, stepping or fast-forwarding
+    let alpha = match context.mode {
+        PhysicsTimeMode::Running { speed } if speed.is_finite() => time.interpolation_alpha(),
+        _ => 1.,
+    };
+
+    for (transform, mut global, mut interpolate, parent) in bodies.iter_mut() {
+        let (Some(previous), Some(current)) = (interpolate.previous, interpolate.current) else { continue; };
+        // moved outside of a tick (restart, step back, editing), don't blend into it
+        if current != *transform {
+            interpolate.previous = Some(*transform);
+            interpolate.current = Some(*transform);
+            continue;
+        }
+        // written even at full alpha: transform propagation skips unchanged transforms, so
+        // an interpolated pose from a previous frame would otherwise stay after pausing
+        let interpolated = if alpha >= 1. {
+            current
+        } else {
+            Transform {
+                translation: previous.translation.lerp(current.translation, alpha),
+                rotation: previous.rotation.slerp(current.rotation, alpha),
+                scale: current.scale,
+            }
+        };
+        let parent_global = parent
+            .and_then(|parent| parents.get(parent.get()).ok())
+            .copied()
+            .unwrap_or_default();
+        *global.bypass_change_detection() = parent_global.mul_transform(interpolated);
+    }
+}
+
+// Body with this marker is simulated as `RigidBody::Fixed`, original body type
+// and velocity are restored when marker is removed.
+#[derive(Component, Debug, Default, Clone, Copy, Reflect)]
+#[reflect(Component)]
+pub struct SimDisabled;
+
+#[derive(Component)]
+struct SimDisabledOriginal {
+    body: RigidBody,
+    velocity: Option<Velocity>,
+}
+
+fn disable_bodies(
+    mut commands: Commands,
+    mut bodies: Query<(Entity, &mut RigidBody, Option<&Velocity>), (Added<SimDisabled>, Without<SimDisabledOriginal>)>,
+) {
+    for (entity, mut body, velocity) in bodies.iter_mut() {
+        commands.entity(entity).insert(SimDisabledOriginal { body: *body, velocity: velocity.copied() });
+        *body = RigidBody::Fixed;
+    }
+}
+
+fn restore_bodies(
+    mut commands: Commands,
+    mut removed: RemovedComponents<SimDisabled>,
+    mut bodies: Query<(&mut RigidBody, Option<&mut Velocity>, &SimDisabledOriginal), Without<SimDisabled>>,
+) {
+    for entity in removed.read() {
+        let Ok((mut body, velocity, original)) = bodies.get_mut(entity) else { continue; };
+        *body = original.body;
+        if let (Some(mut velocity), Some(original)) = (velocity, original.velocity) {
+            *velocity = original;
+        }
+        commands.entity(entity).remove::<SimDisabledOriginal>();
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SolverBand {
+    // band applies to speeds up to this value (inclusive)
+    pub max_speed: f32,
+    pub velocity_iterations: usize,
+    pub stabilization_iterations: usize,
+}
+
+// Solver iterations depending on playback speed, e.g. more iterations for slow-motion analysis
+// and less for fast-forward. First band that fits current speed is used, stepping and AutoStep
+// count as zero speed. Outside of all bands (and by default, with no bands) rapier keeps
+// the parameters it had before any band was applied.
+#[derive(Resource, Debug, Clone, Default)]
+pub struct SolverOverrides {
+    pub bands: Vec<SolverBand>,
+    // (velocity, stabilization) iterations before first override
+    original: Option<(usize, usize)>,
+}
+
+fn apply_solver_overrides(
+    mut overrides: ResMut<SolverOverrides>,
+    time: Res<PhysicsTime>,
+    mut context: ResMut<RapierContext>,
+) {
+    if overrides.bands.is_empty() && overrides.original.is_none() { return; }
+
+    let speed = match time.context().mode {
+        // keep whatever was used before pausing
+        PhysicsTimeMode::Paused => return,
+        PhysicsTimeMode::OneTick
+        | PhysicsTimeMode::NTicks { .. }
+        | PhysicsTimeMode::AutoStep { .. }
+        | PhysicsTimeMode::FixedTicksPerFrame { .. } => 0.,
+        PhysicsTimeMode::Running { speed } => speed,
+    };
+
+    let params = &context.integration_parameters;
+    let current = (params.max_velocity_iterations, params.max_stabilization_iterations);
+    let original = *overrides.original.get_or_insert(current);
+    let wanted = overrides.bands.iter()
+        .find(|band| speed <= band.max_speed)
+        .map(|band| (band.velocity_iterations, band.stabilization_iterations))
+        .unwrap_or(original);
+
+    if wanted != current {
+        let params = &mut context.integration_parameters;
+        params.max_velocity_iterations = wanted.0;
+        params.max_stabilization_iterations = wanted.1;
+    }
+}
 
-impl Plugin for TimePlugin {
-    fn build(&self, app: &mut App) {
-        app
-            .init_schedule(PhysicsSchedule)
-            .register_type::<PhysicsTime>()
-            .init_resource::<PhysicsTime>()
-            .init_resource::<DiagnosticFrameCount>()
-            .register_diagnostic(Diagnostic::new(PHYSICS_FPS, "physics_fps", 10))
-            .add_systems(PhysicsSchedule, diagnosics_count)
-            .add_systems(Update, diagnostics_report)
-            .add_systems(PreUpdate, run_physics_schedule);
+// Wall-clock time per frame after which stepping loop stops, leftover time stays in overstep.
+// `Unbounded` runs all pending ticks no matter how long it takes, e.g. for headless batch runs.
+// Fast-forward would then never yield, so unless it's a bounded run like `run_to_tick` or
+// ticks are capped by `CatchupStrategy`, the default limited budget is used instead.
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Reflect)]
+#[reflect(Resource)]
+pub enum PhysicsExecBudget {
+    Limited(Duration),
+    Unbounded,
+}
+
+impl PhysicsExecBudget {
+    fn exceeded(&self, elapsed: Duration) -> bool {
+        match *self {
+            Self::Limited(limit) => elapsed >= limit,
+            Self::Unbounded => false,
+        }
     }
 }
 
-#[derive(ScheduleLabel, Clone, Debug, PartialEq, Eq, Hash)]
-pub struct PhysicsSchedule;
+impl Default for PhysicsExecBudget {
+    fn default() -> Self {
+        Self::Limited(DEFAULT_PHYSICS_EXEC_BUDGET)
+    }
+}
 
-pub type PhysicsTime = Time<PhysicsTimeInner>;
+// In fast-forward, each frame runs physics for one whole frame of given render rate instead of
+// `PhysicsExecBudget`, so rendering (and waiting for vsync) takes a smaller share of CPU time.
+// The demo app also switches winit to a low-power update mode at that rate meanwhile.
+#[derive(Resource, Debug, Clone, Copy, Reflect)]
+#[reflect(Resource)]
+pub struct FastForwardThrottle {
+    pub enabled: bool,
+    pub render_fps: f32,
+}
 
-pub trait PhysicsTimeExt {
-    fn pause(&mut self);
-    fn resume(&mut self);
-    fn step(&mut self);
-    fn run(&mut self, speed: f32);
+impl FastForwardThrottle {
+    fn exec_budget(&self, mode: PhysicsTimeMode, budget: PhysicsExecBudget) -> PhysicsExecBudget {
+        match (mode, budget) {
+            (PhysicsTimeMode::Running { speed }, PhysicsExecBudget::Limited(limit))
+                if speed.is_infinite() && self.enabled && self.render_fps > 0. =>
+            {
+                PhysicsExecBudget::Limited(Duration::from_secs_f32(self.render_fps.recip()).max(limit))
+            }
+            _ => budget,
+        }
+    }
 }
 
-impl PhysicsTimeExt for PhysicsTime {
-    fn pause(&mut self) {
-        self.context_mut().set_mode(PhysicsTimeMode::Paused);
+impl Default for FastForwardThrottle {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            render_fps: 10.,
+        }
+    }
+}
+
+// A-B repeat: state of dynamic bodies is captured when tick counter reaches `start_tick`,
+// and restored together with the clock when it reaches `end_tick`. Only transforms and
+// velocities are captured, so contacts may resolve slightly differently after the jump.
+#[derive(Resource, Debug, Clone, Copy, Reflect)]
+#[reflect(Resource)]
+pub struct LoopRegion {
+    pub start_tick: u64,
+    pub end_tick: u64,
+    pub enabled: bool,
+}
+
+impl Default for LoopRegion {
+    fn default() -> Self {
+        Self {
+            start_tick: 0,
+            end_tick: 0,
+            enabled: false,
+        }
     }
+}
 
-    fn resume(&mut self) {
-        let old_mode = self.context().old_mode;
-        self.context_mut().set_mode(old_mode);
+// Transforms and velocities of dynamic bodies together with the clock. Restoring rewinds
+// the clock but keeps current mode, contacts may resolve slightly differently afterwards.
+#[derive(Debug, Clone, Default)]
+pub struct BodySnapshot {
+    pub ticks: u64,
+    pub elapsed: Duration,
+    bodies: Vec<(Entity, Transform, Option<Velocity>)>,
+}
+
+impl BodySnapshot {
+    pub fn capture(world: &mut World) -> Self {
+        let time = world.resource::<PhysicsTime>();
+        let ticks = time.elapsed_ticks();
+        let elapsed = time.elapsed();
+        let bodies = world.query::<(Entity, &Transform, Option<&Velocity>, &RigidBody)>()
+            .iter(world)
+            .filter(|(.., body)| matches!(body, RigidBody::Dynamic))
+            .map(|(entity, transform, velocity, _)| (entity, *transform, velocity.copied()))
+            .collect();
+        Self { ticks, elapsed, bodies }
     }
 
-    fn step(&mut self) {
-        self.context_mut().set_mode(PhysicsTimeMode::OneTick);
+    // bodies despawned since capture are skipped
+    pub fn restore(&self, world: &mut World) {
+        let mut query = world.query::<(&mut Transform, Option<&mut Velocity>)>();
+        for (entity, transform, velocity) in self.bodies.iter() {
+            let Ok((mut current_transform, current_velocity)) = query.get_mut(world, *entity) else { continue; };
+            *current_transform = *transform;
+            if let (Some(mut current_velocity), Some(velocity)) = (current_velocity, velocity) {
+                *current_velocity = *velocity;
+            }
+        }
+
+        let mut time = world.resource_mut::<PhysicsTime>();
+        let mut context = *time.context();
+        context.ticks = self.ticks;
+        let mut restored = PhysicsTime::new_with(context);
+        restored.advance_by(self.elapsed);
+        *time = restored;
     }
 
-    fn run(&mut self, speed: f32) {
-        self.context_mut().set_mode(PhysicsTimeMode::Running { speed });
+    pub fn is_empty(&self) -> bool {
+        self.bodies.is_empty()
     }
 }
 
-#[derive(Debug, Copy, Clone, Reflect)]
-#[reflect(Default)]
-pub struct PhysicsTimeInner {
-    pub mode: PhysicsTimeMode,
-    old_mode: PhysicsTimeMode,
-    pub timestep: Duration,
-    pub overstep: Duration,
+#[derive(Resource, Default)]
+struct LoopSnapshot(BodySnapshot);
+
+fn apply_loop_region(world: &mut World) {
+    let region = *world.resource::<LoopRegion>();
+    if !region.enabled || region.end_tick <= region.start_tick { return; }
+    let ticks = world.resource::<PhysicsTime>().elapsed_ticks();
+
+    if ticks == region.start_tick {
+        let snapshot = BodySnapshot::capture(world);
+        world.resource_mut::<LoopSnapshot>().0 = snapshot;
+        return;
+    }
+
+    if ticks != region.end_tick { return; }
+    let snapshot = &world.resource::<LoopSnapshot>().0;
+    // snapshot from a previous run (before restart or region change)
+    if snapshot.ticks != region.start_tick || snapshot.is_empty() { return; }
+    // kept for the next pass through the region
+    snapshot.clone().restore(world);
 }
 
-impl PhysicsTimeInner {
-    pub fn set_mode(&mut self, mode: PhysicsTimeMode) {
-        if let PhysicsTimeMode::Running { .. } = mode {
-            self.old_mode = mode;
-        }
-        self.mode = mode;
+// Condition checked after every tick, simulation pauses right after the tick where it holds
+// (remaining ticks of the frame aren't run) and `PhysicsBreakpointHit` is sent. Not checked
+// while paused, so resuming from a breakpoint runs at least one more tick.
+#[derive(Resource)]
+pub struct PhysicsBreakpoint {
+    pub enabled: bool,
+    condition: Box<dyn Fn(&World) -> bool + Send + Sync>,
+}
+
+impl PhysicsBreakpoint {
+    pub fn new(condition: impl Fn(&World) -> bool + Send + Sync + 'static) -> Self {
+        Self { enabled: true, condition: Box::new(condition) }
     }
 }
 
-impl Default for PhysicsTimeInner {
+#[derive(Event, Debug, Clone, Copy)]
+pub struct PhysicsBreakpointHit {
+    pub ticks: u64,
+    pub elapsed: Duration,
+}
+
+fn check_breakpoint(world: &mut World) -> bool {
+    let Some(breakpoint) = world.get_resource::<PhysicsBreakpoint>() else { return false; };
+    if !breakpoint.enabled || !(breakpoint.condition)(world) { return false; }
+
+    let mut time = world.resource_mut::<PhysicsTime>();
+    time.pause();
+    let event = PhysicsBreakpointHit { ticks: time.elapsed_ticks(), elapsed: time.elapsed() };
+    world.send_event(event);
+    true
+}
+
+// Rapier state of dynamic bodies after each of the last `capacity` ticks, used by
+// `PhysicsTimeExt::step_back`. Positions and velocities are written back into rapier
+// directly, so the next tick continues from exactly the same body state (contacts are
+// recomputed on that tick). Cleared when clock goes back, e.g. on restart.
+//
+// With `quantization` set, translations and velocities are stored as i16 multiples of that step
+// and rotations as i16 fixed-point, about half the memory per body. Restored values are off by
+// at most `step / 2` per component (clamped to `±32767 * step`, e.g. ±32 m and ±32 m/s with
+// 1mm step) and rotations by about 1e-4 rad, so ticks run after step back don't reproduce
+// the original run exactly.
+#[derive(Resource, Reflect)]
+#[reflect(Resource)]
+pub struct RewindBuffer {
+    pub enabled: bool,
+    pub capacity: usize,
+    pub quantization: Option<f32>,
+    #[reflect(ignore)]
+    snapshots: VecDeque<RewindSnapshot>,
+}
+
+impl RewindBuffer {
+    pub fn len(&self) -> usize {
+        self.snapshots.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.snapshots.is_empty()
+    }
+
+    // at least one tick can be undone
+    pub fn can_step_back(&self) -> bool {
+        self.snapshots.len() >= 2
+    }
+}
+
+impl Default for RewindBuffer {
     fn default() -> Self {
         Self {
-            mode:     PhysicsTimeMode::default(),
-            old_mode: PhysicsTimeMode::default(),
-            timestep: DEFAULT_TIMESTEP,
-            overstep: Duration::ZERO,
+            enabled: false,
+            capacity: 300,
+            quantization: None,
+            snapshots: VecDeque::new(),
         }
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Reflect)]
-pub enum PhysicsTimeMode {
-    Paused,
-    OneTick,
-    Running { speed: f32 },
+struct RewindSnapshot {
+    ticks: u64,
+    elapsed: Duration,
+    bodies: RewindBodies,
 }
 
-impl Default for PhysicsTimeMode {
+// position, linear and angular velocity of each dynamic body
+type RewindBody = (RigidBodyHandle, Isometry<f32>, Vector<f32>, Vector<f32>);
+
+enum RewindBodies {
+    Full(Vec<RewindBody>),
+    // translation, rotation (quaternion scaled by `i16::MAX`), linvel and angvel
+    Quantized { step: f32, bodies: Vec<(RigidBodyHandle, [i16; 3], [i16; 4], [i16; 3], [i16; 3])> },
+}
+
+impl RewindBodies {
+    fn new(bodies: impl Iterator<Item = RewindBody>, quantization: Option<f32>) -> Self {
+        let Some(step) = quantization.filter(|step| *step > 0.) else { return Self::Full(bodies.collect()); };
+        let quantize = |value: f32, step: f32| (value / step).round().clamp(-32767., 32767.) as i16;
+        let bodies = bodies
+            .map(|(handle, position, linvel, angvel)| {
+                let translation = position.translation.vector.map(|value| quantize(value, step));
+                let rotation = position.rotation.coords.map(|value| quantize(value, 1. / 32767.));
+                let linvel = linvel.map(|value| quantize(value, step));
+                let angvel = angvel.map(|value| quantize(value, step));
+                (handle, translation.into(), rotation.into(), linvel.into(), angvel.into())
+            })
+            .collect();
+        Self::Quantized { step, bodies }
+    }
+
+    fn restore(&self) -> Vec<RewindBody> {
+        let (step, bodies) = match self {
+            Self::Full(bodies) => return bodies.clone(),
+            Self::Quantized { step, bodies } => (*step, bodies),
+        };
+        let vector = |q: [i16; 3]| Vector::new(q[0] as f32, q[1] as f32, q[2] as f32) * step;
+        bodies.iter()
+            .map(|&(handle, translation, rotation, linvel, angvel)| {
+                let [x, y, z, w] = rotation.map(|q| q as f32 / 32767.);
+                let rotation = UnitQuaternion::new_normalize(Quaternion::new(w, x, y, z));
+                let position = Isometry::from_parts(vector(translation).into(), rotation);
+                (handle, position, vector(linvel), vector(angvel))
+            })
+            .collect()
+    }
+}
+
+fn record_rewind_state(mut buffer: ResMut<RewindBuffer>, context: Res<RapierContext>, time: Res<PhysicsTime>) {
+    if !buffer.enabled {
+        if !buffer.snapshots.is_empty() { buffer.snapshots.clear(); }
+        return;
+    }
+
+    let ticks = time.elapsed_ticks();
+    if buffer.snapshots.back().is_some_and(|last| last.ticks >= ticks) {
+        buffer.snapshots.clear();
+    }
+
+    let bodies = context.bodies.iter()
+        .filter(|(_, body)| body.is_dynamic())
+        .map(|(handle, body)| (handle, *body.position(), *body.linvel(), *body.angvel()));
+    let bodies = RewindBodies::new(bodies, buffer.quantization);
+    buffer.snapshots.push_back(RewindSnapshot { ticks, elapsed: time.elapsed(), bodies });
+    while buffer.snapshots.len() > buffer.capacity.max(2) {
+        buffer.snapshots.pop_front();
+    }
+}
+
+fn apply_step_back(world: &mut World) {
+    let requests = std::mem::take(&mut world.resource_mut::<PhysicsTime>().context_mut().step_back_requests);
+    if requests == 0 { return; }
+
+    let mut buffer = world.resource_mut::<RewindBuffer>();
+    let mut restored = None;
+    for _ in 0..requests {
+        if !buffer.can_step_back() { break; }
+        // latest snapshot is the current state
+        buffer.snapshots.pop_back();
+        restored = buffer.snapshots.back().map(|snapshot| (snapshot.ticks, snapshot.elapsed, snapshot.bodies.restore()));
+    }
+    let Some((ticks, elapsed, bodies)) = restored else {
+        warn!("nothing to step back to, enable RewindBuffer and run some ticks");
+        return;
+    };
+
+    let mut context = world.resource_mut::<RapierContext>();
+    for (handle, position, linvel, angvel) in bodies {
+        let Some(body) = context.bodies.get_mut(handle) else { continue; };
+        body.set_position(position, true);
+        body.set_linvel(linvel, true);
+        body.set_angvel(angvel, true);
+    }
+
+    let mut time = world.resource_mut::<PhysicsTime>();
+    let mut clock = *time.context();
+    clock.ticks = ticks;
+    clock.overstep = Duration::ZERO;
+    let mut rewound = PhysicsTime::new_with(clock);
+    rewound.advance_by(elapsed);
+    *time = rewound;
+
+    force_writeback(world);
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TriggerId(pub u32);
+
+// One-shot triggers on elapsed physics time, each one fires on the first tick that reaches it
+// and is removed afterwards. Every tick is checked separately, so triggers crossed during one
+// fast-forward frame still fire in order.
+#[derive(Resource, Debug, Clone, Default)]
+pub struct TimeTriggers(pub Vec<(Duration, TriggerId)>);
+
+#[derive(Event, Debug, Clone, Copy)]
+pub struct TimeTriggerEvent {
+    pub id: TriggerId,
+}
+
+fn fire_time_triggers(
+    mut triggers: ResMut<TimeTriggers>,
+    mut events: EventWriter<TimeTriggerEvent>,
+    time: Res<PhysicsTime>,
+) {
+    if triggers.0.is_empty() { return; }
+    let elapsed = time.elapsed();
+    if !triggers.0.iter().any(|(at, _)| *at <= elapsed) { return; }
+
+    // stable, so triggers with the same time fire in insertion order
+    triggers.0.sort_by_key(|(at, _)| *at);
+    let count = triggers.0.partition_point(|(at, _)| *at <= elapsed);
+    for (_, id) in triggers.0.drain(..count) {
+        events.send(TimeTriggerEvent { id });
+    }
+}
+
+// timestep changes applied when tick counter reaches given value, e.g. to test how timestep
+// transitions affect stability. Ticks after it use the new timestep, tick 0 applies on restart.
+#[derive(Resource, Debug, Clone, Default)]
+pub struct TimestepSchedule(pub Vec<(u64, Duration)>);
+
+// called once whenever tick counter changes, so a paused clock doesn't keep
+// overriding timestep set by the user
+fn apply_timestep_schedule(world: &mut World) {
+    let tick = world.resource::<PhysicsTime>().elapsed_ticks();
+    let schedule = world.resource::<TimestepSchedule>();
+    let Some(&(_, timestep)) = schedule.0.iter().rev().find(|(at, _)| *at == tick) else { return; };
+    world.resource_mut::<PhysicsTime>().set_timestep(timestep);
+}
+
+// How many ticks can be run in one frame when simulation is behind, leftover time stays
+// in overstep (still limited by `limit_overstep`). Applies together with `PhysicsExecBudget`,
+// whichever stops the loop first; a tick cap alone doesn't depend on machine speed.
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Reflect)]
+#[reflect(Resource)]
+pub enum CatchupStrategy {
+    // run as many ticks as possible within `PhysicsExecBudget`
+    Burst,
+    // run at most one tick plus this many extra ticks per frame
+    Spread { max_extra_per_frame: u32 },
+    // exactly one tick per frame while running, regardless of frame time and speed,
+    // nothing is accumulated, for frame-locked deterministic recordings
+    FrameLocked,
+}
+
+impl CatchupStrategy {
+    pub fn max_ticks(&self) -> u32 {
+        match *self {
+            Self::Burst => u32::MAX,
+            Self::Spread { max_extra_per_frame } => max_extra_per_frame.saturating_add(1),
+            Self::FrameLocked => 1,
+        }
+    }
+}
+
+impl Default for CatchupStrategy {
     fn default() -> Self {
-        Self::Running { speed: 1. }
+        Self::Burst
+    }
+}
+
+// Hard cap on ticks per frame with any `CatchupStrategy`, in all modes except
+// `FixedTicksPerFrame`. Like the strategy, it applies together with `PhysicsExecBudget`,
+// whichever stops the loop first. None (default) leaves only the wall-clock budget and
+// the strategy, zero is treated as one.
+#[derive(Resource, Debug, Clone, Copy, Default, PartialEq, Eq, Reflect)]
+#[reflect(Resource)]
+pub struct MaxTicksPerFrame(pub Option<u32>);
+
+impl MaxTicksPerFrame {
+    fn limit(&self) -> u32 {
+        self.0.map_or(u32::MAX, |ticks| ticks.max(1))
+    }
+}
+
+fn sync_rapier_timestep(time: Res<PhysicsTime>, mut config: ResMut<RapierConfiguration>) {
+    let dt = time.context().timestep.as_secs_f32();
+    if let TimestepMode::Fixed { dt: rapier_dt, .. } = config.timestep_mode {
+        if rapier_dt == dt { return; }
     }
+    config.timestep_mode = TimestepMode::Fixed { dt, substeps: 1 };
 }
 
 fn accumulate_time(time: &mut PhysicsTime, delta: Duration) {
     let context = time.context_mut();
+    if let Some(mut ramp) = context.speed_ramp {
+        ramp.elapsed = ramp.elapsed.saturating_add(delta);
+        if ramp.elapsed >= ramp.duration {
+            context.set_mode(ramp.then);
+        } else {
+            // smoothstep, so speed doesn't change abruptly at either end
+            let t = ramp.elapsed.as_secs_f32() / ramp.duration.as_secs_f32();
+            let eased = t * t * (3. - 2. * t);
+            let speed = ramp.from + (ramp.to - ramp.from) * eased;
+            context.mode = if speed > 0. { PhysicsTimeMode::Running { speed } } else { PhysicsTimeMode::Paused };
+            context.speed_ramp = Some(ramp);
+        }
+    }
     match context.mode {
         PhysicsTimeMode::Paused => (),
         PhysicsTimeMode::OneTick => (),
+        PhysicsTimeMode::NTicks { .. } => (),
+        PhysicsTimeMode::AutoStep { .. } => {
+            context.auto_step_elapsed = context.auto_step_elapsed.saturating_add(delta);
+        }
+        PhysicsTimeMode::FixedTicksPerFrame { ticks } => {
+            context.frame_ticks_left = ticks;
+        }
         PhysicsTimeMode::Running { speed } => {
             if speed == std::f32::INFINITY {
                 context.overstep = Duration::MAX;
@@ -116,15 +1759,46 @@ fn accumulate_time(time: &mut PhysicsTime, delta: Duration) {
     }
 }
 
-fn expend_time(time: &mut PhysicsTime) -> bool {
+fn expend_time(time: &mut PhysicsTime, timeout: RunUntilTimeout) -> bool {
     let context = time.context_mut();
+    // checked before boost end takes `boost_then`
+    let bounded = context.run_target.is_some() || context.boost_then.is_some();
     let result = match context.mode {
         PhysicsTimeMode::Paused => false,
         PhysicsTimeMode::OneTick => {
-            context.mode = PhysicsTimeMode::Paused;
-            context.overstep = Duration::ZERO;
+            // overstep is untouched, this tick is not paid from accumulated time
+            context.override_mode(PhysicsTimeMode::Paused);
+            context.run_target = None;
+            true
+        }
+        PhysicsTimeMode::NTicks { remaining } => {
+            // nothing is accumulated while stepping, so only sub-tick overstep from before remains
+            if remaining > 1 {
+                context.override_mode(PhysicsTimeMode::NTicks { remaining: remaining - 1 });
+            } else {
+                context.override_mode(PhysicsTimeMode::Paused);
+                context.run_target = None;
+            }
             true
         }
+        PhysicsTimeMode::AutoStep { interval } => {
+            // at most one tick per frame, leftover beyond one interval is dropped
+            if context.auto_step_elapsed >= interval {
+                context.auto_step_elapsed = Duration::ZERO;
+                true
+            } else {
+                false
+            }
+        }
+        PhysicsTimeMode::FixedTicksPerFrame { .. } => {
+            // overstep is untouched, nothing is accumulated
+            if context.frame_ticks_left > 0 {
+                context.frame_ticks_left -= 1;
+                true
+            } else {
+                false
+            }
+        }
         PhysicsTimeMode::Running { speed: _ } => {
             if let Some(new_value) = context.overstep.checked_sub(context.timestep) {
                 context.overstep = new_value;
@@ -136,29 +1810,213 @@ fn expend_time(time: &mut PhysicsTime) -> bool {
     };
 
     if result {
+        if let Some(ticks) = context.boost_ticks {
+            if ticks > 1 {
+                context.boost_ticks = Some(ticks - 1);
+            } else {
+                // boosted time left in accumulator shouldn't carry over
+                context.boost_ticks = None;
+                let then = context.boost_then.take().unwrap_or(context.old_mode);
+                context.override_mode(then);
+                context.overstep = Duration::ZERO;
+            }
+        }
+
+        context.ticks += 1;
         let timestep = context.timestep;
         time.advance_by(timestep);
+
+        let elapsed = time.elapsed();
+        let context = time.context_mut();
+        context.reach(elapsed);
+        if context.run_target.is_some_and(|target| elapsed >= target) {
+            context.set_mode(PhysicsTimeMode::Paused);
+        }
+
+        if bounded && context.mode != PhysicsTimeMode::Paused {
+            context.run_until_ticks += 1;
+            if context.run_until_ticks >= timeout.max_ticks {
+                context.set_mode(PhysicsTimeMode::Paused);
+                context.timed_out = Some(context.run_until_ticks);
+            }
+        }
     }
     result
 }
 
-fn limit_overstep(time: &mut PhysicsTime) {
+fn report_run_until_timeout(world: &mut World) {
+    let Some(ticks) = world.resource_mut::<PhysicsTime>().context_mut().timed_out.take() else { return; };
+    world.send_event(RunUntilTimeoutEvent { ticks });
+}
+
+// returns discarded time, except for fast-forward where accumulator is unbounded by design
+fn limit_overstep(time: &mut PhysicsTime) -> Duration {
     let context = time.context_mut();
-    context.overstep = context.overstep.min(context.timestep * 3);
+    let limited = context.overstep.min(context.timestep * 3);
+    let discarded = context.overstep - limited;
+    context.overstep = limited;
+
+    match context.mode {
+        PhysicsTimeMode::Running { speed } if speed.is_infinite() => Duration::ZERO,
+        _ => discarded,
+    }
+}
+
+// How many ticks `run_physics_schedule` will attempt for a frame with given delta.
+// Accounts for the end of a boost or `run_until`, and for carried-over overstep being
+// limited like `limit_overstep` does. Fast-forward is only limited by catch-up strategy
+// and `MaxTicksPerFrame` (u32::MAX for `Burst` without a cap). Not predicted: wall-clock
+// `PhysicsExecBudget` stopping the loop earlier, `StartupRamp` (pass the ramped delta),
+// breakpoints, `RunUntilTimeout`, speed ramps, and timestep changes during the frame.
+pub fn predict_ticks(
+    time: &PhysicsTime,
+    delta: Duration,
+    catchup: CatchupStrategy,
+    max_ticks: MaxTicksPerFrame,
+) -> u32 {
+    let context = time.context();
+    let ticks = match context.mode {
+        PhysicsTimeMode::Paused => 0,
+        PhysicsTimeMode::OneTick => 1,
+        PhysicsTimeMode::NTicks { remaining } => remaining,
+        PhysicsTimeMode::AutoStep { interval } => {
+            (context.auto_step_elapsed.saturating_add(delta) >= interval) as u32
+        }
+        // not limited by catch-up strategy or tick cap
+        PhysicsTimeMode::FixedTicksPerFrame { ticks } => return ticks,
+        PhysicsTimeMode::Running { .. } if catchup == CatchupStrategy::FrameLocked => 1,
+        PhysicsTimeMode::Running { speed } => {
+            if speed == std::f32::INFINITY {
+                u32::MAX
+            } else {
+                let carried = context.overstep.min(context.timestep * 3);
+                let overstep = carried.saturating_add(delta.mul_f32(speed));
+                (overstep.as_nanos() / context.timestep.as_nanos()).min(u32::MAX as u128) as u32
+            }
+        }
+    };
+    // time left after a boost is discarded, so no ticks of the next mode are run this frame
+    let ticks = context.boost_ticks.map_or(ticks, |left| ticks.min(left));
+    // `run_until` pauses on the first tick at or past its target
+    let ticks = match context.run_target {
+        Some(target) => {
+            let left = target.saturating_sub(time.elapsed()).as_nanos();
+            let to_target = left.div_ceil(context.timestep.as_nanos()).max(1);
+            ticks.min(to_target.min(u32::MAX as u128) as u32)
+        }
+        None => ticks,
+    };
+    ticks.min(catchup.max_ticks()).min(max_ticks.limit())
 }
 
 pub fn run_physics_schedule(world: &mut World) {
-    let delta = world.resource::<Time<Virtual>>().delta();
+    let delta = match *world.resource::<AccumulationClock>() {
+        AccumulationClock::Virtual => world.resource::<Time<Virtual>>().delta(),
+        AccumulationClock::Real => world.resource::<Time<Real>>().delta(),
+    };
+    let delta = delta.mul_f32(world.resource_mut::<StartupRamp>().next_factor());
     accumulate_time(&mut world.resource_mut::<PhysicsTime>(), delta);
 
+    let catchup = *world.resource::<CatchupStrategy>();
+    let fixed_ticks = matches!(world.resource::<PhysicsTime>().context().mode, PhysicsTimeMode::FixedTicksPerFrame { .. });
+    let max_ticks = if fixed_ticks {
+        u32::MAX
+    } else {
+        catchup.max_ticks().min(world.resource::<MaxTicksPerFrame>().limit())
+    };
+    let frame_locked = catchup == CatchupStrategy::FrameLocked
+        && matches!(world.resource::<PhysicsTime>().context().mode, PhysicsTimeMode::Running { .. });
+    if frame_locked {
+        let mut time = world.resource_mut::<PhysicsTime>();
+        let context = time.context_mut();
+        if let PhysicsTimeMode::Running { speed } = context.mode {
+            if speed != 1. {
+                static SPEED_WARNING: std::sync::Once = std::sync::Once::new();
+                SPEED_WARNING.call_once(|| warn!("speed is ignored with CatchupStrategy::FrameLocked"));
+            }
+        }
+        context.overstep = context.timestep;
+    }
+    let real_delta = world.resource::<Time<Real>>().delta();
+    let elapsed_before = world.resource::<PhysicsTime>().elapsed();
+    let exec_budget = world.resource::<FastForwardThrottle>()
+        .exec_budget(world.resource::<PhysicsTime>().context().mode, *world.resource::<PhysicsExecBudget>());
+    let context = world.resource::<PhysicsTime>().context();
+    let endless_fast_forward = context.mode == PhysicsTimeMode::Running { speed: f32::INFINITY }
+        && context.boost_ticks.is_none()
+        && context.run_target.is_none()
+        && max_ticks == u32::MAX;
+    let exec_budget = match exec_budget {
+        _ if fixed_ticks => PhysicsExecBudget::Unbounded,
+        PhysicsExecBudget::Limited(limit) if context.seeking => {
+            PhysicsExecBudget::Limited(limit.max(SEEK_EXEC_BUDGET))
+        }
+        PhysicsExecBudget::Unbounded if endless_fast_forward => {
+            static UNBOUNDED_WARNING: std::sync::Once = std::sync::Once::new();
+            UNBOUNDED_WARNING.call_once(|| warn!("unbounded exec budget is limited in fast-forward that never stops"));
+            PhysicsExecBudget::default()
+        }
+        budget => budget,
+    };
+
+    let timeout = *world.resource::<RunUntilTimeout>();
+
     let time = std::time::Instant::now();
     world.schedule_scope(PhysicsSchedule, |world, schedule| {
-        while expend_time(&mut world.resource_mut::<PhysicsTime>()) {
+        let mut ticks = 0;
+        while ticks < max_ticks {
+            if !expend_time(&mut world.resource_mut::<PhysicsTime>(), timeout) { break; }
+            report_run_until_timeout(world);
+            send_tick_event(world, false);
             schedule.run(world);
-            if time.elapsed() >= MAX_PHYSICS_EXEC_TIME { break; }
+            apply_timestep_schedule(world);
+            apply_loop_region(world);
+            ticks += 1;
+            if check_breakpoint(world) { break; }
+            if exec_budget.exceeded(time.elapsed()) {
+                world.resource_mut::<DroppedTime>().budget_exceeded_frames += 1;
+                break;
+            }
         }
-        limit_overstep(&mut world.resource_mut::<PhysicsTime>());
+        *world.resource_mut::<PhysicsFrameCost>() = PhysicsFrameCost { duration: time.elapsed(), ticks };
+        if frame_locked {
+            // leftover frame time is dropped by design, not reported as lost
+            world.resource_mut::<PhysicsTime>().context_mut().overstep = Duration::ZERO;
+            return;
+        }
+        let discarded = limit_overstep(&mut world.resource_mut::<PhysicsTime>());
+        world.resource_mut::<DroppedTime>().total += discarded;
+        react_to_clamped_overstep(world, discarded);
     });
+
+    let mut time = world.resource_mut::<PhysicsTime>();
+    let advanced = time.elapsed().saturating_sub(elapsed_before);
+    let context = time.context_mut();
+    if context.mode.approx_eq(&PhysicsTimeMode::Running { speed: 1. }, SPEED_EPSILON) {
+        context.drift += real_delta.as_secs_f64() - advanced.as_secs_f64();
+    }
+    context.measured_speed = if real_delta.is_zero() {
+        0.
+    } else {
+        (advanced.as_secs_f64() / real_delta.as_secs_f64()) as f32
+    };
+}
+
+fn check_time_advance(time: Res<PhysicsTime>, mut last: Local<Option<(u64, Duration, Duration)>>) {
+    let context = time.context();
+    let current = (context.ticks, time.elapsed(), context.timestep);
+    let Some((last_ticks, last_elapsed, last_timestep)) = last.replace(current) else { return; };
+
+    // restart or jump back, and timestep changes can't be checked this way
+    if current.0 < last_ticks || current.1 < last_elapsed || current.2 != last_timestep { return; }
+
+    let ticks = current.0 - last_ticks;
+    // can't overflow with any realistic number of ticks, skipped rather than truncated anyway
+    let Some(expected) = u32::try_from(ticks).ok().and_then(|ticks| last_timestep.checked_mul(ticks)) else { return; };
+    let advanced = current.1 - last_elapsed;
+    if advanced != expected {
+        warn!("physics time advanced by {:?} in {} ticks, expected {:?}", advanced, ticks, expected);
+    }
 }
 
 fn diagnosics_count(mut frame_count: ResMut<DiagnosticFrameCount>) {
@@ -169,6 +2027,9 @@ fn diagnostics_report(
     mut diagnostics: Diagnostics,
     mut frame_count: ResMut<DiagnosticFrameCount>,
     time: Res<Time<Real>>,
+    physics_time: Res<PhysicsTime>,
+    dropped: Res<DroppedTime>,
+    mut last_dropped: Local<Duration>,
 ) {
     let delta = time.delta_seconds_f64();
     if delta == 0. { return; }
@@ -176,4 +2037,224 @@ fn diagnostics_report(
         frame_count.0 as f64 / delta
     });
     frame_count.0 = 0;
+    diagnostics.add_measurement(SIM_DRIFT, || physics_time.context().drift_secs());
+    diagnostics.add_measurement(SIM_SPEED, || physics_time.measured_speed() as f64);
+    // total is never reset, but can be replaced by the user
+    let dropped_since = dropped.total.saturating_sub(*last_dropped);
+    *last_dropped = dropped.total;
+    diagnostics.add_measurement(DROPPED_TIME, || dropped_since.as_secs_f64() / delta);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // one frame of the stepping loop without running any schedules, returns ticks executed
+    fn run_frame(time: &mut PhysicsTime, delta: Duration) -> u32 {
+        accumulate_time(time, delta);
+        let mut ticks = 0;
+        while expend_time(time, RunUntilTimeout::default()) {
+            ticks += 1;
+        }
+        ticks
+    }
+
+    #[test]
+    fn step_from_paused_runs_one_tick() {
+        let mut time = PhysicsTime::default();
+        time.pause();
+        time.step();
+        assert_eq!(run_frame(&mut time, DEFAULT_TIMESTEP * 4), 1);
+        assert_eq!(time.context().mode, PhysicsTimeMode::Paused);
+        assert_eq!(run_frame(&mut time, DEFAULT_TIMESTEP * 4), 0);
+    }
+
+    #[test]
+    fn step_from_running_keeps_overstep() {
+        let mut time = PhysicsTime::default();
+        assert_eq!(run_frame(&mut time, DEFAULT_TIMESTEP * 3 / 2), 1);
+        let overstep = time.context().overstep;
+        assert_eq!(overstep, DEFAULT_TIMESTEP / 2);
+
+        time.step();
+        assert_eq!(run_frame(&mut time, DEFAULT_TIMESTEP * 4), 1);
+        assert_eq!(time.context().mode, PhysicsTimeMode::Paused);
+        assert_eq!(time.context().overstep, overstep);
+        assert_eq!(time.elapsed_ticks(), 2);
+
+        time.resume();
+        assert_eq!(time.context().mode, PhysicsTimeMode::Running { speed: 1. });
+        assert_eq!(run_frame(&mut time, DEFAULT_TIMESTEP / 2), 1);
+        assert_eq!(time.context().overstep, Duration::ZERO);
+    }
+
+    #[test]
+    fn step_from_n_ticks_replaces_remaining() {
+        let mut time = PhysicsTime::default();
+        time.step_by(5);
+        time.step();
+        assert_eq!(run_frame(&mut time, DEFAULT_TIMESTEP), 1);
+        assert_eq!(time.context().mode, PhysicsTimeMode::Paused);
+    }
+
+    #[test]
+    fn step_from_auto_step_resumes_it() {
+        let mut time = PhysicsTime::default();
+        let interval = Duration::from_secs(1);
+        time.auto_step(interval);
+        time.step();
+        assert_eq!(run_frame(&mut time, Duration::from_millis(10)), 1);
+        assert_eq!(time.context().mode, PhysicsTimeMode::Paused);
+
+        time.resume();
+        assert_eq!(time.context().mode, PhysicsTimeMode::AutoStep { interval });
+        assert_eq!(run_frame(&mut time, Duration::from_millis(10)), 0);
+        assert_eq!(run_frame(&mut time, interval), 1);
+    }
+
+    #[test]
+    fn mode_string_round_trip() {
+        let modes = [
+            PhysicsTimeMode::Paused,
+            PhysicsTimeMode::OneTick,
+            PhysicsTimeMode::NTicks { remaining: 10 },
+            PhysicsTimeMode::Running { speed: 1. },
+            PhysicsTimeMode::Running { speed: 0.25 },
+            PhysicsTimeMode::Running { speed: f32::INFINITY },
+            PhysicsTimeMode::AutoStep { interval: Duration::from_millis(500) },
+            PhysicsTimeMode::FixedTicksPerFrame { ticks: 4 },
+        ];
+        for mode in modes {
+            let text = mode.to_string();
+            assert_eq!(text.parse::<PhysicsTimeMode>(), Ok(mode), "{}", text);
+        }
+        assert_eq!(PhysicsTimeMode::Running { speed: 1. }.to_string(), "running:1.0");
+        assert_eq!(PhysicsTimeMode::Running { speed: f32::INFINITY }.to_string(), "running:inf");
+        assert_eq!(" Running ".parse::<PhysicsTimeMode>(), Ok(PhysicsTimeMode::Running { speed: 1. }));
+    }
+
+    #[test]
+    fn mode_string_rejects_invalid_speed() {
+        for text in ["running:0", "running:-1", "running:-inf", "running:NaN", "running:", "n_ticks:0", "fixed_ticks:0", "walking"] {
+            assert!(text.parse::<PhysicsTimeMode>().is_err(), "{}", text);
+        }
+    }
+
+    #[test]
+    fn run_without_positive_speed_pauses() {
+        for speed in [0., -1., f32::NAN] {
+            let mut time = PhysicsTime::default();
+            time.run(speed);
+            assert!(time.is_paused(), "{}", speed);
+            assert_eq!(time.direction(), PlaybackDirection::Stopped);
+        }
+    }
+
+    #[test]
+    fn elapsed_accessors_follow_ticks() {
+        let mut time = PhysicsTime::default();
+        assert_eq!(time.elapsed_secs_f64(), 0.);
+        assert_eq!(time.interpolation_alpha(), 0.);
+
+        assert_eq!(run_frame(&mut time, DEFAULT_TIMESTEP * 5 / 2), 2);
+        assert_eq!(time.elapsed_ticks(), 2);
+        assert_eq!(time.tick_count(), 2);
+        assert_eq!(time.elapsed_secs_f64(), DEFAULT_TIMESTEP.as_secs_f64() * 2.);
+        assert_eq!(time.timestep(), DEFAULT_TIMESTEP);
+        assert_eq!(time.overstep(), DEFAULT_TIMESTEP / 2);
+        assert_eq!(time.interpolation_alpha(), 0.5);
+
+        time.set_timestep(DEFAULT_TIMESTEP * 2);
+        assert_eq!(time.timestep(), DEFAULT_TIMESTEP * 2);
+        assert_eq!(time.interpolation_alpha(), 0.25);
+    }
+
+    #[test]
+    fn bounded_progress_counts_boost_ticks() {
+        let mut time = PhysicsTime::default();
+        assert_eq!(time.bounded_progress(), None);
+        time.boost(2., 10);
+        assert_eq!(time.bounded_progress(), Some((10, 10)));
+        assert_eq!(run_frame(&mut time, DEFAULT_TIMESTEP), 2);
+        assert_eq!(time.bounded_progress(), Some((8, 10)));
+        assert_eq!(run_frame(&mut time, DEFAULT_TIMESTEP * 4), 8);
+        assert_eq!(time.bounded_progress(), None);
+    }
+
+    #[test]
+    fn bounded_progress_counts_ticks_to_run_target() {
+        let mut time = PhysicsTime::default();
+        time.run_for(DEFAULT_TIMESTEP * 10);
+        assert_eq!(time.bounded_progress(), Some((10, 10)));
+        assert_eq!(run_frame(&mut time, DEFAULT_TIMESTEP * 4), 4);
+        assert_eq!(time.bounded_progress(), Some((6, 10)));
+        assert_eq!(run_frame(&mut time, DEFAULT_TIMESTEP * 8), 6);
+        assert_eq!(time.bounded_progress(), None);
+        assert!(time.is_paused());
+    }
+
+    #[test]
+    fn fixed_ticks_send_one_event_per_tick() {
+        let mut app = App::new();
+        app
+            .add_plugins((
+                MinimalPlugins,
+                TransformPlugin,
+                HierarchyPlugin,
+                RapierPhysicsPlugin::<NoUserData>::default().with_default_system_setup(false),
+                TimePlugin::minimal(),
+            ));
+        app.world.resource_mut::<PhysicsTime>().fixed_ticks_per_frame(3);
+        let mut reader = app.world.resource::<Events<PhysicsTick>>().get_reader();
+
+        app.update();
+        let events = app.world.resource::<Events<PhysicsTick>>();
+        let ticks: Vec<_> = reader.read(events).map(|event| (event.tick, event.forced)).collect();
+        assert_eq!(ticks, [(1, false), (2, false), (3, false)]);
+
+        force_single_step(&mut app.world);
+        let events = app.world.resource::<Events<PhysicsTick>>();
+        let ticks: Vec<_> = reader.read(events).map(|event| (event.tick, event.forced)).collect();
+        assert_eq!(ticks, [(4, true)]);
+    }
+
+    #[test]
+    fn quantized_rewind_state_round_trips() {
+        let step = 0.001;
+        let handle = RigidBodyHandle::from_raw_parts(3, 1);
+        let position = Isometry::new(Vector::new(1.2345, -0.0004, 20.), Vector::new(0.3, -1.2, 0.7));
+        let linvel = Vector::new(-4.5678, 0.0006, 9.81);
+        let angvel = Vector::new(0.1234, -2., 31.);
+        let bodies = RewindBodies::new([(handle, position, linvel, angvel)].into_iter(), Some(step));
+        assert!(matches!(bodies, RewindBodies::Quantized { .. }));
+
+        let [(restored_handle, restored, restored_linvel, restored_angvel)] = bodies.restore()[..] else { panic!() };
+        let tolerance = step / 2. + f32::EPSILON * 64.;
+        assert_eq!(restored_handle, handle);
+        assert!((restored.translation.vector - position.translation.vector).amax() <= tolerance);
+        assert!(restored.rotation.angle_to(&position.rotation) < 1e-3);
+        assert!((restored_linvel - linvel).amax() <= tolerance);
+        assert!((restored_angvel - angvel).amax() <= tolerance);
+
+        let full = RewindBodies::new([(handle, position, linvel, angvel)].into_iter(), None);
+        assert_eq!(full.restore(), [(handle, position, linvel, angvel)]);
+    }
+
+    #[test]
+    fn furthest_reached_is_kept_for_backward_seek() {
+        let mut time = PhysicsTime::default();
+        assert_eq!(run_frame(&mut time, DEFAULT_TIMESTEP * 4), 4);
+        time.seek(DEFAULT_TIMESTEP);
+        assert!(time.context().seek_restart_pending());
+
+        time = PhysicsTime::new_with(time.context().restarted());
+        assert_eq!(time.furthest_reached(), DEFAULT_TIMESTEP * 4);
+        assert_eq!(run_frame(&mut time, Duration::ZERO), 1);
+        assert!(time.is_paused());
+        assert_eq!(time.furthest_reached(), DEFAULT_TIMESTEP * 4);
+
+        // plain restart starts a new run
+        time = PhysicsTime::new_with(time.context().restarted());
+        assert_eq!(time.furthest_reached(), Duration::ZERO);
+    }
 }
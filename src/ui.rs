@@ -1,10 +1,18 @@
+use std::time::Duration;
+
 use bevy::diagnostic::DiagnosticsStore;
 use bevy::prelude::*;
-use bevy_inspector_egui::bevy_egui::EguiContexts;
+use bevy_inspector_egui::bevy_egui::{EguiContexts, EguiSet};
 use bevy_inspector_egui::egui;
+use bevy_rapier3d::prelude::{DebugRenderContext, RigidBody};
 
-use crate::time::{PhysicsTime, PhysicsTimeExt, PhysicsTimeMode};
-use crate::RestartEvent;
+use crate::camera::OrbitCamera;
+use crate::recording::{TrajectoryRecorder, TrajectoryRecording};
+use crate::rng::SimRng;
+use crate::settle::SettleDetector;
+use crate::stats::RunStatistics;
+use crate::time::{DroppedTime, FastForwardThrottle, LoopRegion, PhysicsBreakpointHit, PhysicsFrameCost, RewindBuffer, PhysicsTime, PhysicsTimeExt, PhysicsTimeMode, SimRestart, SPEED_EPSILON};
+use crate::{GravityScale, KeyboardCaptured};
 
 const ICON_RESTART: char = '\u{E800}';
 const ICON_PAUSE:   char = '\u{E801}';
@@ -12,6 +20,13 @@ const ICON_PLAY:    char = '\u{E802}';
 const ICON_FASTFWD: char = '\u{E803}';
 const ICON_STEP:    char = '\u{E804}';
 
+// ticks run by shift-clicking step button
+const STEP_BY_TICKS: u32 = 10;
+
+const TIMESTEP_RATES: [u32; 6] = [30, 50, 60, 64, 120, 240];
+// speeds with a button next to speed field, 1x and max speed have their own icons
+const SPEED_PRESETS: [f32; 3] = [0.5, 2., 4.];
+
 pub struct UiPlugin;
 
 impl Plugin for UiPlugin {
@@ -19,8 +34,31 @@ impl Plugin for UiPlugin {
         app
             .init_resource::<UiSettings>()
             .register_type::<UiSettings>()
+            .init_resource::<PausedEdit>()
+            .init_resource::<ActionToast>()
+            .init_resource::<PhysicsControlBindings>()
+            .register_type::<PhysicsControlBindings>()
+            .init_resource::<GridSnap>()
+            .register_type::<GridSnap>()
             .add_systems(Startup, init_icon_font)
-            .add_systems(Update, display_custom_window);
+            .add_systems(PreUpdate, capture_keyboard.after(EguiSet::BeginFrame))
+            .add_systems(Update, (
+                display_custom_window,
+                display_settings_window,
+                momentary_play,
+                toast_on_breakpoint,
+                display_action_toast
+                    .after(display_custom_window)
+                    .after(momentary_play)
+                    .after(toast_on_breakpoint),
+                display_accumulator_strip,
+                detect_paused_edits,
+                snap_paused_edits,
+                display_paused_edit_window.after(detect_paused_edits),
+                draw_viewer_gizmos,
+                warn_binding_conflicts.run_if(resource_changed::<PhysicsControlBindings>()),
+                toggle_debug_render.run_if(crate::keyboard_free),
+            ));
     }
 }
 
@@ -28,6 +66,8 @@ impl Plugin for UiPlugin {
 #[reflect(Resource)]
 struct UiSettings {
     enabled: bool,
+    // multiplies all sizes below, e.g. for high-DPI screens or streaming
+    ui_scale: f32,
     margin_top: f32,
     icon_font_size: f32,
     info_font_size: f32,
@@ -36,12 +76,35 @@ struct UiSettings {
     spacing_before: f32,
     spacing_after: f32,
     height: f32,
+    show_grid: bool,
+    grid_extent: f32,
+    grid_spacing: f32,
+    show_axes: bool,
+    // strip showing how frame time is split into ticks, for learning how accumulator works
+    show_accumulator: bool,
+    // speed averaged over last few frames, otherwise the one from last frame is shown
+    smooth_speed_display: bool,
+    // progress bar while a bounded run (e.g. boost) is active
+    show_bounded_progress: bool,
+    // show how far simulation fell behind real time at 1x, if more than this (seconds)
+    drift_badge_threshold: f64,
+    auto_step_interval: Duration,
+    // briefly show which action was triggered from keyboard
+    show_action_toasts: bool,
+    // if false, simulation continues with previous speed after restart
+    pause_after_restart: bool,
+    // simulation runs only while this key is held
+    momentary_play_key: Option<KeyCode>,
+    momentary_play_speed: f32,
+    // play, fast-forward and preset buttons change speed gradually over this time
+    speed_ramp: Duration,
 }
 
 impl Default for UiSettings {
     fn default() -> Self {
         Self {
             enabled: true,
+            ui_scale: 1.,
             margin_top: 2.,
             icon_font_size: 22.,
             info_font_size: 16.,
@@ -50,10 +113,216 @@ impl Default for UiSettings {
             spacing_before: 15.,
             spacing_after: 15.,
             height: 20.,
+            show_grid: false,
+            grid_extent: 10.,
+            grid_spacing: 1.,
+            show_axes: false,
+            show_accumulator: false,
+            smooth_speed_display: true,
+            show_bounded_progress: true,
+            drift_badge_threshold: 0.1,
+            auto_step_interval: Duration::from_millis(500),
+            show_action_toasts: true,
+            pause_after_restart: true,
+            momentary_play_key: Some(KeyCode::Period),
+            momentary_play_speed: 1.,
+            speed_ramp: Duration::ZERO,
+        }
+    }
+}
+
+impl UiSettings {
+    fn sizes(&self) -> UiSizes {
+        let scale = self.ui_scale;
+        UiSizes {
+            margin_top: self.margin_top * scale,
+            icon_font_size: self.icon_font_size * scale,
+            info_font_size: self.info_font_size * scale,
+            line_height: self.line_height * scale,
+            spacing: self.spacing * scale,
+            spacing_before: self.spacing_before * scale,
+            spacing_after: self.spacing_after * scale,
+            height: self.height * scale,
+        }
+    }
+}
+
+// size fields of `UiSettings` with `ui_scale` applied
+struct UiSizes {
+    margin_top: f32,
+    icon_font_size: f32,
+    info_font_size: f32,
+    line_height: f32,
+    spacing: f32,
+    spacing_before: f32,
+    spacing_after: f32,
+    height: f32,
+}
+
+// set when a body was moved (e.g. via inspector) while simulation is paused
+#[derive(Resource, Default)]
+struct PausedEdit {
+    pending: bool,
+}
+
+// rounds positions of bodies moved while paused, for authoring clean initial conditions
+#[derive(Reflect, Resource)]
+#[reflect(Resource)]
+pub struct GridSnap {
+    pub spacing: f32,
+    pub enabled: bool,
+}
+
+impl Default for GridSnap {
+    fn default() -> Self {
+        Self {
+            spacing: 0.5,
+            enabled: false,
         }
     }
 }
 
+const TOAST_DURATION: f64 = 1.;
+
+// keys for widget buttons and other controls, override with `insert_resource`
+#[derive(Reflect, Resource)]
+#[reflect(Resource)]
+pub struct PhysicsControlBindings {
+    pub pause: Option<KeyCode>,
+    pub step: Option<KeyCode>,
+    pub play: Option<KeyCode>,
+    pub fast_forward: Option<KeyCode>,
+    pub restart: Option<KeyCode>,
+    // collider wireframes, requires `RapierDebugRenderPlugin`
+    pub debug_render: Option<KeyCode>,
+}
+
+impl Default for PhysicsControlBindings {
+    fn default() -> Self {
+        Self {
+            pause: Some(KeyCode::Space),
+            step: Some(KeyCode::Slash),
+            play: None,
+            fast_forward: None,
+            restart: None,
+            debug_render: Some(KeyCode::F8),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ControlAction {
+    Restart,
+    Pause,
+    Step,
+    Play,
+    FastForward,
+    DebugRender,
+}
+
+impl ControlAction {
+    fn for_icon(icon: char) -> Option<Self> {
+        match icon {
+            ICON_RESTART => Some(Self::Restart),
+            ICON_PAUSE   => Some(Self::Pause),
+            ICON_STEP    => Some(Self::Step),
+            ICON_PLAY    => Some(Self::Play),
+            ICON_FASTFWD => Some(Self::FastForward),
+            _ => None,
+        }
+    }
+}
+
+impl PhysicsControlBindings {
+    // in widget order, which decides who keeps a key bound to several actions
+    fn actions(&self) -> [(ControlAction, Option<KeyCode>); 6] {
+        [
+            (ControlAction::Restart, self.restart),
+            (ControlAction::Pause, self.pause),
+            (ControlAction::Step, self.step),
+            (ControlAction::Play, self.play),
+            (ControlAction::FastForward, self.fast_forward),
+            (ControlAction::DebugRender, self.debug_render),
+        ]
+    }
+
+    // None if unbound or the key is already taken by an earlier action
+    fn key_for(&self, action: ControlAction) -> Option<KeyCode> {
+        let actions = self.actions();
+        let index = actions.iter().position(|(other, _)| *other == action)?;
+        let key = actions[index].1?;
+        if actions[..index].iter().any(|(_, other)| *other == Some(key)) { return None; }
+        Some(key)
+    }
+}
+
+fn key_label(key: KeyCode) -> String {
+    match key {
+        KeyCode::Slash => "/".to_string(),
+        KeyCode::Period => ".".to_string(),
+        KeyCode::Comma => ",".to_string(),
+        key => format!("{:?}", key),
+    }
+}
+
+fn warn_binding_conflicts(bindings: Res<PhysicsControlBindings>, settings: Res<UiSettings>) {
+    for (action, key) in bindings.actions() {
+        let Some(key) = key else { continue; };
+        if bindings.key_for(action) != Some(key) {
+            warn!("{:?} is bound to several actions, only the first one is used", key);
+        } else if settings.momentary_play_key == Some(key) {
+            warn!("{:?} is also used for momentary play", key);
+        }
+    }
+}
+
+fn toggle_debug_render(
+    bindings: Res<PhysicsControlBindings>,
+    keys: Res<Input<KeyCode>>,
+    context: Option<ResMut<DebugRenderContext>>,
+) {
+    let Some(key) = bindings.key_for(ControlAction::DebugRender) else { return; };
+    if !keys.just_pressed(key) { return; }
+    let Some(mut context) = context else { return; };
+    context.enabled = !context.enabled;
+}
+
+// widget button highlighted for given mode
+fn active_icon(mode: PhysicsTimeMode) -> char {
+    match mode {
+        PhysicsTimeMode::Paused => ICON_PAUSE,
+        PhysicsTimeMode::OneTick => ICON_STEP,
+        PhysicsTimeMode::NTicks { .. } => ICON_STEP,
+        PhysicsTimeMode::AutoStep { .. } => ICON_STEP,
+        PhysicsTimeMode::FixedTicksPerFrame { .. } => ICON_STEP,
+        PhysicsTimeMode::Running { .. } => {
+            if mode.approx_eq(&PhysicsTimeMode::Running { speed: 1. }, SPEED_EPSILON) {
+                ICON_PLAY
+            } else {
+                ICON_FASTFWD
+            }
+        }
+    }
+}
+
+#[derive(Resource, Default)]
+struct ActionToast {
+    pending: Option<(char, &'static str)>,
+    // icon, text and real time when it was shown
+    active: Option<(char, &'static str, f64)>,
+}
+
+impl ActionToast {
+    fn show(&mut self, icon: char, text: &'static str) {
+        self.pending = Some((icon, text));
+    }
+}
+
+fn capture_keyboard(mut egui_contexts: EguiContexts, mut captured: ResMut<KeyboardCaptured>) {
+    let wants_keyboard = egui_contexts.ctx_mut().wants_keyboard_input();
+    captured.set_if_neq(KeyboardCaptured(wants_keyboard));
+}
+
 fn init_icon_font(mut contexts: EguiContexts) {
     let mut fonts = egui::FontDefinitions::default();
 
@@ -71,29 +340,41 @@ fn init_icon_font(mut contexts: EguiContexts) {
     contexts.ctx_mut().set_fonts(fonts);
 }
 
+#[allow(clippy::too_many_arguments)]
 fn display_custom_window(
     mut egui_contexts: EguiContexts,
-    settings: ResMut<UiSettings>,
+    settings: Res<UiSettings>,
     mut time: ResMut<PhysicsTime>,
-    mut restart_events: EventWriter<RestartEvent>,
+    mut restart_events: EventWriter<SimRestart>,
     diagnostics: Res<DiagnosticsStore>,
     keys: Res<Input<KeyCode>>,
-    mut last_fps: Local<f64>,
+    mut toast: ResMut<ActionToast>,
+    rewind: Res<RewindBuffer>,
+    dropped: Res<DroppedTime>,
+    recorder: Res<TrajectoryRecorder>,
+    loop_region: Res<LoopRegion>,
+    mut timeline_drag: Local<Option<f64>>,
+    mut last_speed: Local<Option<f32>>,
+    bindings: Res<PhysicsControlBindings>,
+    keyboard: Res<KeyboardCaptured>,
 ) {
     if !settings.enabled { return; }
+    let sizes = settings.sizes();
     let ctx = egui_contexts.ctx_mut();
+    // shortcuts shouldn't fire while typing into a text field
+    let typing = keyboard.0;
 
     let font = egui::FontId::new(
-        settings.icon_font_size,
+        sizes.icon_font_size,
         egui::FontFamily::Name("sim_icons".into()),
     );
 
     egui::Window::new("widget")
-        .anchor(egui::Align2::CENTER_TOP, egui::vec2(0., settings.margin_top))
+        .anchor(egui::Align2::CENTER_TOP, egui::vec2(0., sizes.margin_top))
         .title_bar(false)
         .auto_sized()
         .show(ctx, |ui| {
-            ui.set_height(settings.height);
+            ui.set_height(sizes.height);
             ui.with_layout(egui::Layout::left_to_right(egui::Align::Center), |ui| {
                 let elapsed = time.elapsed();
                 ui.label(
@@ -105,26 +386,33 @@ fn display_custom_window(
                             elapsed.as_secs() % 60,
                             elapsed.subsec_millis(),
                         )
-                    ).size(settings.info_font_size),
+                    ).size(sizes.info_font_size),
                 );
 
-                let active_icon = match time.context().mode {
-                    PhysicsTimeMode::Paused => ICON_PAUSE,
-                    PhysicsTimeMode::OneTick => ICON_STEP,
-                    PhysicsTimeMode::Running { speed } => {
-                        if speed == 1. {
-                            ICON_PLAY
-                        } else {
-                            ICON_FASTFWD
-                        }
-                    }
-                };
+                let normal_speed = PhysicsTimeMode::Running { speed: 1. };
+                let max_speed = PhysicsTimeMode::Running { speed: std::f32::INFINITY };
 
-                ui.add_space(settings.spacing_before);
+                let active_icon = active_icon(time.context().mode);
+
+                ui.add_space(sizes.spacing_before);
                 for (idx, icon) in [ICON_RESTART, ICON_PAUSE, ICON_STEP, ICON_PLAY, ICON_FASTFWD].into_iter().enumerate() {
-                    if idx > 0 { ui.add_space(settings.spacing); }
+                    if idx > 0 { ui.add_space(sizes.spacing); }
 
-                    let base_color = if active_icon == icon {
+                    // icon font has no step back glyph, default egui font has one
+                    if icon == ICON_STEP && rewind.enabled {
+                        let text = egui::RichText::new("⏪").size(sizes.icon_font_size * 0.8);
+                        let response = ui.add_enabled(rewind.can_step_back(), egui::Button::new(text).frame(false))
+                            .on_hover_text("Undo one simulation step")
+                            .on_disabled_hover_text("No recorded steps to undo");
+                        if response.clicked() {
+                            time.step_back();
+                        }
+                        ui.add_space(sizes.spacing);
+                    }
+
+                    let base_color = if icon == ICON_STEP && time.step_pending() {
+                        egui::Color32::from_rgb(255, 230, 100)
+                    } else if active_icon == icon {
                         if icon == ICON_PAUSE {
                             egui::Color32::from_rgb(255, 128, 128)
                         } else {
@@ -138,98 +426,727 @@ fn display_custom_window(
                     ui.style_mut().visuals.widgets.hovered.fg_stroke.color = base_color.gamma_multiply(0.9);
                     ui.style_mut().visuals.widgets.active.fg_stroke.color = base_color;
 
-                    let text = egui::RichText::new(icon).font(font.clone()).line_height(Some(settings.line_height));
+                    let text = egui::RichText::new(icon).font(font.clone()).line_height(Some(sizes.line_height));
                     let label = egui::Label::new(text).sense(egui::Sense::click());
 
                     let response = ui.add(label);
-                    let key = match icon {
-                        ICON_PAUSE => Some(KeyCode::Space),
-                        ICON_STEP  => Some(KeyCode::Slash),
-                        _ => None,
-                    };
+                    let key = ControlAction::for_icon(icon).and_then(|action| bindings.key_for(action));
 
                     let response = response.on_hover_ui(|ui| {
-                        match icon {
-                            ICON_RESTART => { ui.label("Restart simulation from the beginning"); },
-                            ICON_PAUSE   => {
-                                ui.with_layout(egui::Layout::left_to_right(egui::Align::Min), |ui| {
-                                    ui.label("Pause simulation");
-                                    ui.label(egui::RichText::new("Space").italics());
-                                });
+                        let text = match icon {
+                            ICON_RESTART => "Restart simulation from the beginning",
+                            ICON_PAUSE   => "Pause simulation",
+                            ICON_STEP    => "Run one simulation step",
+                            ICON_PLAY    => "Run simulation with normal speed",
+                            ICON_FASTFWD => "Fast-Forward simulation with maximum speed",
+                            _ => return,
+                        };
+                        ui.with_layout(egui::Layout::left_to_right(egui::Align::Min), |ui| {
+                            ui.label(text);
+                            if let Some(key) = key {
+                                ui.label(egui::RichText::new(key_label(key)).italics());
                             }
-                            ICON_STEP    => {
-                                ui.with_layout(egui::Layout::left_to_right(egui::Align::Min), |ui| {
-                                    ui.label("Run one simulation step");
-                                    ui.label(egui::RichText::new("/").italics());
-                                });
-                            }
-                            ICON_PLAY    => { ui.label("Run simulation with normal speed"); },
-                            ICON_FASTFWD => { ui.label("Fast-Forward simulation with maximum speed"); },
-                            _ => (),
+                        });
+                        if icon == ICON_STEP {
+                            ui.with_layout(egui::Layout::left_to_right(egui::Align::Min), |ui| {
+                                ui.label(format!("Run {} simulation steps", STEP_BY_TICKS));
+                                if let Some(key) = key {
+                                    ui.label(egui::RichText::new(format!("Shift + {}", key_label(key))).italics());
+                                }
+                            });
                         }
                     });
 
                     let key_pressed = match key {
-                        Some(key) => keys.just_pressed(key),
+                        Some(key) => !typing && keys.just_pressed(key),
                         None => false,
                     };
 
                     if key_pressed || response.clicked() {
                         match icon {
                             ICON_RESTART => {
-                                restart_events.send(RestartEvent);
-                                time.pause();
-                            }
-                            ICON_PAUSE => {
-                                if time.context().mode == PhysicsTimeMode::Paused {
-                                    time.resume();
-                                } else {
+                                restart_events.send(SimRestart::default());
+                                if settings.pause_after_restart {
                                     time.pause();
                                 }
                             }
+                            ICON_PAUSE => time.toggle_pause(),
                             ICON_STEP => {
-                                time.step();
+                                if keys.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]) {
+                                    time.step_by(STEP_BY_TICKS);
+                                } else {
+                                    time.step();
+                                }
                             }
                             ICON_PLAY => {
-                                if time.context().mode == (PhysicsTimeMode::Running { speed: 1. }) {
+                                if time.context().mode.approx_eq(&normal_speed, SPEED_EPSILON) {
                                     time.pause();
                                 } else {
-                                    time.run(1.);
+                                    time.run_eased(1., settings.speed_ramp);
                                 }
                             }
                             ICON_FASTFWD => {
-                                if time.context().mode == (PhysicsTimeMode::Running { speed: std::f32::INFINITY }) {
+                                if time.context().mode.approx_eq(&max_speed, SPEED_EPSILON) {
                                     time.pause();
                                 } else {
-                                    time.run(std::f32::INFINITY);
+                                    time.run_eased(std::f32::INFINITY, settings.speed_ramp);
                                 }
                             }
                             _ => (),
                         }
+
+                        if key_pressed {
+                            let text = match icon {
+                                ICON_PAUSE if time.is_paused() => "Paused",
+                                ICON_PAUSE => "Resumed",
+                                ICON_STEP => "Step",
+                                ICON_PLAY => "Play",
+                                ICON_FASTFWD => "Fast-Forward",
+                                ICON_RESTART => "Restart",
+                                _ => "",
+                            };
+                            toast.show(icon, text);
+                        }
                     }
                 }
-                ui.add_space(settings.spacing_after);
+                ui.add_space(sizes.spacing_after);
 
+                // actual speed rather than requested one, so it's visible when physics can't keep up
                 let speed = match time.context().mode {
                     PhysicsTimeMode::Paused => 0.,
                     PhysicsTimeMode::OneTick => 0.,
-                    PhysicsTimeMode::Running { speed } => {
-                        let expected_fps = time.context().timestep.as_secs_f64().recip();
-                        let measured_fps = diagnostics.get(crate::time::PHYSICS_FPS).unwrap().average().unwrap_or_default();
-                        let speed_factor = speed as f64;
+                    PhysicsTimeMode::NTicks { .. } => 0.,
+                    PhysicsTimeMode::AutoStep { .. }
+                    | PhysicsTimeMode::FixedTicksPerFrame { .. }
+                    | PhysicsTimeMode::Running { .. } => {
+                        let measured = diagnostics.get(crate::time::SIM_SPEED)
+                            .and_then(|diagnostic| if settings.smooth_speed_display {
+                                diagnostic.average()
+                            } else {
+                                diagnostic.value()
+                            });
+                        measured.unwrap_or(time.measured_speed() as f64)
+                    }
+                };
 
-                        let actual_fps = last_fps.max(measured_fps);
-                        *last_fps = measured_fps;
+                // fast-forward can reach thousands, digits after the point are just noise there
+                let speed_text = if speed >= 1000. { ">999x".to_string() } else { format!("{:.2}x", speed) };
+                ui.label(egui::RichText::new(speed_text).size(sizes.info_font_size));
 
-                        let mut result = actual_fps / expected_fps;
-                        if result > speed_factor * 0.95 {
-                            result = speed_factor;
+                // Requested speed. While paused, stepping or in fast-forward it shows the last finite
+                // speed that was requested, prefixed with current mode, since it isn't in effect.
+                let running = time.speed().filter(|speed| speed.is_finite() && *speed > 0.);
+                if running.is_some() {
+                    *last_speed = running;
+                }
+                let mut requested = running.or(*last_speed).unwrap_or(1.);
+                let prefix = match time.context().mode {
+                    PhysicsTimeMode::Running { speed } if speed.is_infinite() => "ff ",
+                    PhysicsTimeMode::Running { .. } => "",
+                    PhysicsTimeMode::Paused => "paused ",
+                    _ => "step ",
+                };
+                let response = ui.add(
+                    egui::DragValue::new(&mut requested)
+                        .speed(0.01)
+                        .clamp_range(0.0..=1000.0)
+                        .max_decimals(2)
+                        .prefix(prefix)
+                        .suffix("x"),
+                ).on_hover_text("Run simulation with this speed");
+                if response.changed() {
+                    // too slow to ever see a tick, same as zero
+                    if requested < 0.01 {
+                        time.pause();
+                    } else {
+                        time.run(requested);
+                    }
+                }
+
+                for preset in SPEED_PRESETS {
+                    let active = time.context().mode.approx_eq(&PhysicsTimeMode::Running { speed: preset }, SPEED_EPSILON);
+                    if ui.selectable_label(active, format!("{}x", preset)).clicked() {
+                        time.run_eased(preset, settings.speed_ramp);
+                    }
+                }
+
+                let drift = time.context().drift_secs();
+                if drift > settings.drift_badge_threshold {
+                    ui.label(
+                        egui::RichText::new(format!("-{:.2}s", drift))
+                            .size(sizes.info_font_size)
+                            .color(egui::Color32::from_rgb(255, 128, 128)),
+                    ).on_hover_text("Simulation is behind real time, configured rate can't be sustained");
+                }
+
+                // diagnostic is missing with `TimePlugin::minimal()`
+                let dropped_rate = diagnostics.get(crate::time::DROPPED_TIME)
+                    .and_then(|diagnostic| diagnostic.average())
+                    .unwrap_or_default();
+                if dropped_rate > 0. {
+                    ui.label(
+                        egui::RichText::new(format!("dropped {:.0}ms/s", dropped_rate * 1000.))
+                            .size(sizes.info_font_size)
+                            .color(egui::Color32::from_rgb(255, 128, 128)),
+                    ).on_hover_text(format!(
+                        "Accumulated time is discarded, physics can't keep up with configured rate\n\
+                         frames over execution budget: {}",
+                        dropped.budget_exceeded_frames,
+                    ));
+                }
+
+                // Timeline over the recorded ticks, or up to the furthest time reached without
+                // a recording, seeks where the handle is released. Seeking can't go past its end,
+                // so it never runs into physics not seen before.
+                let (start, end) = recorder.recording.time_range()
+                    .unwrap_or((Duration::ZERO, time.furthest_reached()));
+                let (start, end) = (start.as_secs_f64(), end.as_secs_f64());
+                let mut position = timeline_drag.unwrap_or(time.elapsed_secs_f64()).clamp(start, end);
+                let response = ui.add(
+                    egui::Slider::new(&mut position, start..=end.max(start + 1e-3))
+                        .show_value(false),
+                ).on_hover_text("Seek, earlier time is re-simulated from the start");
+                timeline_marks(ui, &response, start..=end, time.context().timestep, &loop_region, &recorder.recording);
+                let position = position.clamp(start, end);
+                if response.dragged() {
+                    *timeline_drag = Some(position);
+                }
+                if response.drag_released() || (response.changed() && !response.dragged()) {
+                    *timeline_drag = None;
+                    time.seek(Duration::from_secs_f64(position));
+                }
+                if timeline_drag.is_some() && position >= end {
+                    ui.label(egui::RichText::new("end").size(sizes.info_font_size).color(egui::Color32::from_rgb(255, 200, 100)))
+                        .on_hover_text("End of recorded time, seeking stops here");
+                }
+
+                if let Some((remaining, total)) = time.bounded_progress() {
+                    if settings.show_bounded_progress && total > 0 {
+                        let done = 1. - remaining as f32 / total as f32;
+                        ui.add(
+                            egui::ProgressBar::new(done)
+                                .desired_width(100. * settings.ui_scale)
+                                .text(format!("{} ticks left", remaining)),
+                        );
+                    }
+                }
+            });
+        });
+}
+
+#[allow(clippy::too_many_arguments)]
+fn display_settings_window(
+    mut egui_contexts: EguiContexts,
+    settings: Res<UiSettings>,
+    mut gravity: ResMut<GravityScale>,
+    mut settle: ResMut<SettleDetector>,
+    mut time: ResMut<PhysicsTime>,
+    stats: Res<RunStatistics>,
+    diagnostics: Res<DiagnosticsStore>,
+    debug_render: Option<ResMut<DebugRenderContext>>,
+    mut recorder: ResMut<TrajectoryRecorder>,
+    mut annotation_text: Local<String>,
+    mut rng: ResMut<SimRng>,
+    mut throttle: ResMut<FastForwardThrottle>,
+    mut loop_region: ResMut<LoopRegion>,
+    mut rewind: ResMut<RewindBuffer>,
+) {
+    if !settings.enabled { return; }
+    let sizes = settings.sizes();
+    let ctx = egui_contexts.ctx_mut();
+
+    egui::Window::new("Simulation")
+        .anchor(egui::Align2::RIGHT_TOP, egui::vec2(-sizes.margin_top, sizes.margin_top))
+        .default_open(false)
+        .resizable(false)
+        .show(ctx, |ui| {
+            // edit a copy, so change detection only triggers on user input,
+            // scale set from code outside of the slider range isn't clamped
+            let mut gravity_scale = gravity.scale;
+            let response = ui.add(egui::Slider::new(&mut gravity_scale, 0.0..=5.0).text("gravity"));
+            if response.changed() {
+                gravity.scale = gravity_scale;
+            }
+
+            if let Some(mut debug_render) = debug_render {
+                let mut enabled = debug_render.enabled;
+                ui.checkbox(&mut enabled, "Show colliders");
+                if enabled != debug_render.enabled {
+                    debug_render.enabled = enabled;
+                }
+            }
+
+            ui.checkbox(&mut rewind.enabled, "Record steps for undo")
+                .on_hover_text(format!("Keeps last {} ticks", rewind.capacity));
+
+            ui.checkbox(&mut throttle.enabled, "Throttle rendering in fast-forward")
+                .on_hover_text(format!("Render at ~{} fps while fast-forwarding, physics gets the rest", throttle.render_fps));
+
+            let current_rate = time.context().timestep.as_secs_f64().recip();
+            egui::ComboBox::from_label("physics rate")
+                .selected_text(format!("{:.0} Hz", current_rate))
+                .show_ui(ui, |ui| {
+                    for rate in TIMESTEP_RATES {
+                        let selected = (current_rate - rate as f64).abs() < 0.01;
+                        if ui.selectable_label(selected, format!("{} Hz", rate)).clicked() {
+                            time.set_timestep(Duration::from_secs_f64(1. / rate as f64));
                         }
-                        result
                     }
+                });
+
+            // only on user input, rate from timestep doesn't survive the round trip exactly
+            // and the slider clamps rates outside of its range
+            let mut rate = current_rate;
+            let response = ui.add(egui::Slider::new(&mut rate, 10.0..=480.0).logarithmic(true).suffix(" Hz"));
+            if response.changed() {
+                time.set_timestep(Duration::from_secs_f64(1. / rate));
+            }
+
+            ui.horizontal(|ui| {
+                let mut seed = rng.seed();
+                ui.label("seed");
+                ui.add(egui::DragValue::new(&mut seed));
+                if seed != rng.seed() {
+                    rng.set_seed(seed);
+                }
+            }).response.on_hover_text("Used on next restart");
+
+            let auto_step_label = format!("Auto-step every {} ms", settings.auto_step_interval.as_millis());
+            if ui.button(auto_step_label).clicked() {
+                time.auto_step(settings.auto_step_interval);
+            }
+
+            if ui.add_enabled(!settle.active, egui::Button::new("Run until settled")).clicked() {
+                settle.start();
+                if time.is_paused() {
+                    time.resume();
+                }
+            }
+
+            egui::CollapsingHeader::new("Last run").show(ui, |ui| {
+                let Some(summary) = stats.summary else {
+                    ui.label("Pause simulation to see statistics");
+                    return;
                 };
 
-                ui.label(egui::RichText::new(format!("{:.2}x", speed)).size(settings.info_font_size));
+                egui::Grid::new("last_run").show(ui, |ui| {
+                    ui.label("ticks");
+                    ui.label(summary.ticks.to_string());
+                    ui.end_row();
+                    ui.label("sim time");
+                    ui.label(format!("{:.3}s", summary.sim_time.as_secs_f64()));
+                    ui.end_row();
+                    ui.label("avg / peak fps");
+                    ui.label(format!("{:.1} / {:.1}", summary.average_fps, summary.peak_fps));
+                    ui.end_row();
+                    ui.label("peak contacts");
+                    ui.label(summary.peak_contacts.to_string());
+                    ui.end_row();
+                    ui.label("dropped time");
+                    ui.label(format!("{:.3}s", summary.dropped_time.as_secs_f64()));
+                    ui.end_row();
+                });
+            });
+
+            egui::CollapsingHeader::new("Recording").show(ui, |ui| {
+                recording_section(ui, &mut recorder, &mut annotation_text);
+            });
+
+            egui::CollapsingHeader::new("Loop region").show(ui, |ui| {
+                loop_region_section(ui, &mut loop_region, time.elapsed_ticks());
+            });
+
+            egui::CollapsingHeader::new("Physics FPS").show(ui, |ui| {
+                let values = diagnostics.get(crate::time::PHYSICS_FPS)
+                    .map(|diagnostic| diagnostic.measurements().map(|measurement| measurement.value).collect::<Vec<_>>())
+                    .unwrap_or_default();
+                let expected_fps = time.context().timestep.as_secs_f64().recip();
+                let running = matches!(time.context().mode, PhysicsTimeMode::Running { .. });
+                fps_graph(ui, &values, expected_fps, running);
+            });
+        });
+}
+
+fn recording_section(ui: &mut egui::Ui, recorder: &mut TrajectoryRecorder, annotation_text: &mut String) {
+    ui.checkbox(&mut recorder.enabled, "Record trajectories");
+    ui.label(format!(
+        "{} ticks, {:.1} MB",
+        recorder.recording.ticks.len(),
+        recorder.memory_usage() as f64 / 1024. / 1024.,
+    ));
+
+    let last_tick = recorder.recording.last_tick();
+    ui.horizontal(|ui| {
+        ui.text_edit_singleline(annotation_text);
+        let can_annotate = last_tick.is_some() && !annotation_text.is_empty();
+        if ui.add_enabled(can_annotate, egui::Button::new("Annotate")).clicked() {
+            if let Some(tick) = last_tick {
+                recorder.recording.annotate(tick, std::mem::take(annotation_text));
+            }
+        }
+    });
+
+    for (tick, notes) in recorder.recording.annotations.iter() {
+        for note in notes {
+            ui.label(format!("#{}: {}", tick, note));
+        }
+    }
+}
+
+// Loop region and annotations painted over the timeline slider, same mapping as the handle.
+// Hovering an annotation marker shows its notes.
+fn timeline_marks(
+    ui: &egui::Ui,
+    slider: &egui::Response,
+    range: std::ops::RangeInclusive<f64>,
+    timestep: Duration,
+    region: &LoopRegion,
+    recording: &TrajectoryRecording,
+) {
+    // slider keeps handle radius free at both ends of the rail
+    let rail = slider.rect.shrink2(egui::vec2(slider.rect.height() / 2.5, 0.));
+    let (start, end) = range.into_inner();
+    let to_x = |secs: f64| {
+        let t = ((secs - start) / (end - start).max(1e-3)).clamp(0., 1.) as f32;
+        rail.left() + t * rail.width()
+    };
+    let painter = ui.painter_at(slider.rect);
+
+    if region.end_tick > region.start_tick {
+        let tick_x = |tick: u64| to_x(tick as f64 * timestep.as_secs_f64());
+        let color = if region.enabled {
+            egui::Color32::from_rgba_unmultiplied(80, 160, 80, 90)
+        } else {
+            egui::Color32::from_rgba_unmultiplied(120, 120, 120, 60)
+        };
+        painter.rect_filled(
+            egui::Rect::from_x_y_ranges(tick_x(region.start_tick)..=tick_x(region.end_tick), slider.rect.y_range()),
+            0.,
+            color,
+        );
+    }
+
+    let hover_x = slider.hover_pos().map(|pos| pos.x);
+    for (tick, notes) in recording.annotations.iter() {
+        let x = to_x(*tick as f64 * recording.timestep.as_secs_f64());
+        let color = egui::Color32::from_rgb(255, 200, 100);
+        painter.line_segment([egui::pos2(x, slider.rect.top()), egui::pos2(x, slider.rect.bottom())], (2., color));
+        if hover_x.is_some_and(|hover_x| (hover_x - x).abs() <= 3.) {
+            egui::show_tooltip_at_pointer(ui.ctx(), slider.id.with(("annotation", tick)), |ui| {
+                ui.label(format!("#{}: {}", tick, notes.join("\n")));
+            });
+        }
+    }
+}
+
+// A and B buttons take current tick, with a strip showing the region on a tick axis
+fn loop_region_section(ui: &mut egui::Ui, region: &mut LoopRegion, current_tick: u64) {
+    // edit a copy, so change detection only triggers on actual changes
+    let mut edited = *region;
+    ui.horizontal(|ui| {
+        if ui.button("Set A").clicked() { edited.start_tick = current_tick; }
+        if ui.button("Set B").clicked() { edited.end_tick = current_tick; }
+        ui.checkbox(&mut edited.enabled, "Loop");
+    });
+    ui.label(format!("A: tick {}, B: tick {}", edited.start_tick, edited.end_tick));
+    if edited.enabled && edited.end_tick <= edited.start_tick {
+        ui.label(egui::RichText::new("B should be after A").color(egui::Color32::from_rgb(255, 128, 128)));
+    }
+
+    // timeline from zero to the furthest of current tick and region end
+    let (rect, _) = ui.allocate_exact_size(egui::vec2(200., 12.), egui::Sense::hover());
+    let painter = ui.painter_at(rect);
+    painter.rect_filled(rect, 0., egui::Color32::from_gray(20));
+    let max_tick = current_tick.max(edited.end_tick).max(1) as f32;
+    let to_x = |tick: u64| rect.left() + tick as f32 / max_tick * rect.width();
+    if edited.end_tick > edited.start_tick {
+        let color = if edited.enabled { egui::Color32::from_rgb(80, 160, 80) } else { egui::Color32::from_gray(70) };
+        painter.rect_filled(
+            egui::Rect::from_x_y_ranges(to_x(edited.start_tick)..=to_x(edited.end_tick), rect.y_range()),
+            0.,
+            color,
+        );
+    }
+    let x = to_x(current_tick);
+    painter.line_segment([egui::pos2(x, rect.top()), egui::pos2(x, rect.bottom())], (2., egui::Color32::WHITE));
+
+    if edited.start_tick != region.start_tick || edited.end_tick != region.end_tick || edited.enabled != region.enabled {
+        *region = edited;
+    }
+}
+
+// measured physics fps with a line at expected fps, frames below it are shaded red
+fn fps_graph(ui: &mut egui::Ui, values: &[f64], expected_fps: f64, show_overload: bool) {
+    let (rect, _) = ui.allocate_exact_size(egui::vec2(200., 60.), egui::Sense::hover());
+    let painter = ui.painter_at(rect);
+    painter.rect_filled(rect, 0., egui::Color32::from_gray(20));
+
+    let max_value = values.iter().copied().fold(expected_fps, f64::max) * 1.1;
+    let to_y = |value: f64| rect.bottom() - (value / max_value) as f32 * rect.height();
+    let step = rect.width() / (values.len().max(2) - 1) as f32;
+    let to_x = |idx: usize| rect.left() + idx as f32 * step;
+
+    if show_overload {
+        for (idx, value) in values.iter().enumerate() {
+            if *value >= expected_fps { continue; }
+            let x = to_x(idx);
+            painter.rect_filled(
+                egui::Rect::from_x_y_ranges(x - step / 2. ..= x + step / 2., to_y(expected_fps) ..= to_y(*value)),
+                0.,
+                egui::Color32::from_rgba_unmultiplied(255, 64, 64, 60),
+            );
+        }
+    }
+
+    let target_y = to_y(expected_fps);
+    painter.line_segment(
+        [egui::pos2(rect.left(), target_y), egui::pos2(rect.right(), target_y)],
+        egui::Stroke::new(1., egui::Color32::YELLOW),
+    );
+
+    let points = values.iter().enumerate().map(|(idx, value)| egui::pos2(to_x(idx), to_y(*value))).collect();
+    painter.add(egui::Shape::line(points, egui::Stroke::new(1.5, egui::Color32::LIGHT_GREEN)));
+}
+
+fn momentary_play(
+    keyboard: Res<KeyboardCaptured>,
+    settings: Res<UiSettings>,
+    keys: Res<Input<KeyCode>>,
+    mut time: ResMut<PhysicsTime>,
+    mut held_from: Local<Option<PhysicsTimeMode>>,
+    mut toast: ResMut<ActionToast>,
+) {
+    let Some(key) = settings.momentary_play_key else { return; };
+    let momentary_mode = PhysicsTimeMode::Running { speed: settings.momentary_play_speed };
+
+    // mode is overridden, so `old_mode` (used by resume) stays intact
+    // release is still handled while typing, so the key can't get stuck
+    if keys.just_pressed(key) && !keyboard.0 {
+        *held_from = Some(time.context().mode);
+        time.context_mut().override_mode(momentary_mode);
+        toast.show(ICON_PLAY, "Play while held");
+    }
+
+    if keys.just_released(key) {
+        let Some(prior_mode) = held_from.take() else { return; };
+        // if mode was changed while key was held (e.g. paused), keep it
+        if time.context().mode == momentary_mode {
+            time.context_mut().override_mode(prior_mode);
+        }
+    }
+}
+
+fn detect_paused_edits(
+    time: Res<PhysicsTime>,
+    bodies: Query<Ref<Transform>, With<RigidBody>>,
+    mut paused_edit: ResMut<PausedEdit>,
+    mut last_elapsed: Local<Duration>,
+) {
+    // if physics ran since last check, transform changes come from rapier writeback
+    let physics_ran = time.elapsed() != *last_elapsed;
+    *last_elapsed = time.elapsed();
+
+    if physics_ran || !time.is_paused() {
+        paused_edit.pending = false;
+        return;
+    }
+
+    if bodies.iter().any(|transform| transform.is_changed() && !transform.is_added()) {
+        paused_edit.pending = true;
+    }
+}
+
+fn snap_paused_edits(
+    snap: Res<GridSnap>,
+    time: Res<PhysicsTime>,
+    mut bodies: Query<&mut Transform, (Changed<Transform>, With<RigidBody>)>,
+    mut last_elapsed: Local<Duration>,
+) {
+    // same as in `detect_paused_edits`, ignore changes made by physics
+    let physics_ran = time.elapsed() != *last_elapsed;
+    *last_elapsed = time.elapsed();
+
+    if !snap.enabled || snap.spacing <= 0. { return; }
+    if physics_ran || !time.is_paused() { return; }
+
+    for mut transform in bodies.iter_mut() {
+        if transform.is_added() { continue; }
+        let snapped = (transform.translation / snap.spacing).round() * snap.spacing;
+        // only write if different, otherwise it would be detected as a change again
+        if snapped != transform.translation {
+            transform.translation = snapped;
+        }
+    }
+}
+
+fn display_paused_edit_window(
+    mut egui_contexts: EguiContexts,
+    settings: Res<UiSettings>,
+    mut time: ResMut<PhysicsTime>,
+    mut paused_edit: ResMut<PausedEdit>,
+) {
+    if !settings.enabled || !paused_edit.pending { return; }
+    let sizes = settings.sizes();
+    let ctx = egui_contexts.ctx_mut();
+
+    egui::Window::new("paused_edit")
+        .anchor(egui::Align2::CENTER_TOP, egui::vec2(0., sizes.margin_top + sizes.height + 20. * settings.ui_scale))
+        .title_bar(false)
+        .auto_sized()
+        .show(ctx, |ui| {
+            if ui.button("Apply edit and step once").clicked() {
+                time.step();
+                paused_edit.pending = false;
+            }
+        });
+}
+
+fn draw_viewer_gizmos(
+    mut gizmos: Gizmos,
+    settings: Res<UiSettings>,
+    cameras: Query<&OrbitCamera>,
+) {
+    if settings.show_grid && settings.grid_spacing > 0. {
+        let extent = settings.grid_extent;
+        let lines = (extent / settings.grid_spacing).floor() as i32;
+        let color = Color::rgba(1., 1., 1., 0.2);
+        for i in -lines..=lines {
+            let offset = i as f32 * settings.grid_spacing;
+            gizmos.line(Vec3::new(offset, 0., -extent), Vec3::new(offset, 0., extent), color);
+            gizmos.line(Vec3::new(-extent, 0., offset), Vec3::new(extent, 0., offset), color);
+        }
+    }
+
+    if settings.show_axes {
+        for camera in cameras.iter() {
+            let focus = camera.focus();
+            gizmos.ray(focus, Vec3::X, Color::RED);
+            gizmos.ray(focus, Vec3::Y, Color::GREEN);
+            gizmos.ray(focus, Vec3::Z, Color::BLUE);
+        }
+    }
+}
+
+fn toast_on_breakpoint(mut events: EventReader<PhysicsBreakpointHit>, mut toast: ResMut<ActionToast>) {
+    if events.is_empty() { return; }
+    events.clear();
+    toast.show(ICON_PAUSE, "Breakpoint");
+}
+
+// toast lives in its own area, so it stays visible over the 3d view rather than in the panel
+fn display_action_toast(
+    mut egui_contexts: EguiContexts,
+    settings: Res<UiSettings>,
+    mut toast: ResMut<ActionToast>,
+    time: Res<Time<Real>>,
+) {
+    let now = time.elapsed_seconds_f64();
+    if let Some((icon, text)) = toast.pending.take() {
+        toast.active = Some((icon, text, now));
+    }
+
+    let Some((icon, text, shown_at)) = toast.active else { return; };
+    let age = now - shown_at;
+    if !settings.show_action_toasts || age >= TOAST_DURATION {
+        toast.active = None;
+        return;
+    }
+
+    let sizes = settings.sizes();
+    let alpha = (1. - age / TOAST_DURATION) as f32;
+    let color = egui::Color32::WHITE.gamma_multiply(alpha);
+    let font = egui::FontId::new(
+        sizes.icon_font_size * 2.,
+        egui::FontFamily::Name("sim_icons".into()),
+    );
+
+    egui::Area::new("action_toast")
+        .anchor(egui::Align2::CENTER_CENTER, egui::vec2(0., 0.))
+        .interactable(false)
+        .show(egui_contexts.ctx_mut(), |ui| {
+            ui.vertical_centered(|ui| {
+                ui.label(egui::RichText::new(icon).font(font).color(color));
+                ui.label(egui::RichText::new(text).size(sizes.info_font_size).color(color));
             });
         });
 }
+
+// Accumulator of the last frame on a time axis, with timestep grid:
+// gray is overstep left from previous frame, blue is time added this frame,
+// green are executed ticks and orange is overstep carried to the next frame.
+fn display_accumulator_strip(
+    mut egui_contexts: EguiContexts,
+    settings: Res<UiSettings>,
+    time: Res<PhysicsTime>,
+    cost: Res<PhysicsFrameCost>,
+    mut last_overstep: Local<Duration>,
+) {
+    let previous_overstep = std::mem::replace(&mut *last_overstep, time.context().overstep);
+    if !settings.enabled || !settings.show_accumulator { return; }
+    let sizes = settings.sizes();
+
+    egui::Window::new("Accumulator")
+        .anchor(egui::Align2::CENTER_BOTTOM, egui::vec2(0., -sizes.margin_top))
+        .resizable(false)
+        .collapsible(false)
+        .show(egui_contexts.ctx_mut(), |ui| {
+            let context = time.context();
+            if context.overstep == Duration::MAX {
+                ui.label("Fast-forward, accumulator is unbounded");
+                return;
+            }
+
+            let timestep = context.timestep.as_secs_f32();
+            let executed = timestep * cost.ticks as f32;
+            let overstep = context.overstep.as_secs_f32();
+            let carried = previous_overstep.as_secs_f32().min(executed + overstep);
+            let total = (executed + overstep).max(timestep);
+
+            let (rect, _) = ui.allocate_exact_size(egui::vec2(300. * settings.ui_scale, 36. * settings.ui_scale), egui::Sense::hover());
+            let painter = ui.painter_at(rect);
+            painter.rect_filled(rect, 0., egui::Color32::from_gray(20));
+            let to_x = |secs: f32| rect.left() + secs / total * rect.width();
+            let (top, bottom) = (rect.top(), rect.bottom());
+            let middle = (top + bottom) / 2.;
+            let segment = |from: f32, to: f32, y: std::ops::RangeInclusive<f32>| {
+                egui::Rect::from_x_y_ranges(to_x(from)..=to_x(to), y)
+            };
+
+            // upper half: where accumulated time came from
+            painter.rect_filled(segment(0., carried, top..=middle), 0., egui::Color32::from_gray(90));
+            painter.rect_filled(segment(carried, executed + overstep, top..=middle), 0., egui::Color32::from_rgb(80, 120, 220));
+            // lower half: how it was spent
+            painter.rect_filled(segment(0., executed, middle..=bottom), 0., egui::Color32::from_rgb(80, 180, 80));
+            painter.rect_filled(segment(executed, executed + overstep, middle..=bottom), 0., egui::Color32::from_rgb(230, 150, 50));
+
+            let mut grid = timestep;
+            while grid < total {
+                let x = to_x(grid);
+                painter.line_segment([egui::pos2(x, top), egui::pos2(x, bottom)], (1., egui::Color32::WHITE));
+                grid += timestep;
+            }
+
+            ui.label(format!(
+                "{} ticks of {:.1} ms, overstep {:.1} ms",
+                cost.ticks,
+                timestep * 1000.,
+                overstep * 1000.,
+            ));
+        });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn zero_speed_highlights_pause_icon() {
+        let mut time = PhysicsTime::default();
+        time.run(0.);
+        assert_eq!(active_icon(time.context().mode), ICON_PAUSE);
+        time.run(1.);
+        assert_eq!(active_icon(time.context().mode), ICON_PLAY);
+        time.run(2.);
+        assert_eq!(active_icon(time.context().mode), ICON_FASTFWD);
+    }
+}